@@ -1,16 +1,37 @@
-use open_gal::{CircuitConfig, TableData};
+use open_gal::{CircuitConfig, GlobalTerm, OutputEnable, TableData};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct TableDataWrapper {
     #[serde(rename = "inputPins")]
     pub input_pins: Vec<u32>,
-    #[serde(rename = "outputPin")]
+    /// ignored for a global term, so it can be left out
+    #[serde(rename = "outputPin", default)]
     pub output_pin: u32,
     #[serde(rename = "table")]
     pub table: Vec<bool>,
     #[serde(rename = "dff")]
     pub enable_flip_flop: bool,
+    #[serde(rename = "dontCare", default, skip_serializing_if = "Vec::is_empty")]
+    pub dont_care: Vec<usize>,
+    #[serde(
+        rename = "activeLow",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub active_low: bool,
+    #[serde(rename = "oe", default, skip_serializing_if = "Option::is_none")]
+    pub oe: Option<OutputEnableWrapper>,
+    #[serde(rename = "global", default, skip_serializing_if = "Option::is_none")]
+    pub global: Option<GlobalTerm>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OutputEnableWrapper {
+    #[serde(rename = "inputPins")]
+    pub input_pins: Vec<u32>,
+    #[serde(rename = "table")]
+    pub table: Vec<bool>,
 }
 
 impl From<TableDataWrapper> for TableData {
@@ -20,6 +41,13 @@ impl From<TableDataWrapper> for TableData {
             input_pins: wrapper.input_pins,
             output_pin: wrapper.output_pin,
             table: wrapper.table,
+            dont_care: wrapper.dont_care,
+            active_low: wrapper.active_low,
+            oe: wrapper.oe.map(|oe| OutputEnable {
+                input_pins: oe.input_pins,
+                table: oe.table,
+            }),
+            global: wrapper.global,
         }
     }
 }
@@ -49,13 +77,28 @@ pub struct CircuitConfigWrapper {
 
 impl From<CircuitConfigWrapper> for CircuitConfig {
     fn from(wrapper: CircuitConfigWrapper) -> Self {
-        Self {
-            num_fuses: wrapper.num_fuses,
-            num_pins: wrapper.num_pins,
-            inputs: wrapper.inputs,
-            outputs: wrapper.outputs,
-            special_pins: wrapper.special_pins,
-        }
+        CircuitConfig::new(
+            wrapper.num_fuses,
+            wrapper.num_pins,
+            wrapper.inputs,
+            wrapper.outputs,
+            wrapper.special_pins,
+        )
+    }
+}
+
+/// Loads a device description. Files with a fuse layout are read as is, the older format which
+/// only lists the pins describes a 22V10 style device.
+pub fn load_config(json: &str) -> Result<CircuitConfig, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|err| format!("invalid json: {}", err))?;
+
+    if value.get("layout").is_some() {
+        CircuitConfig::from_json(json)
+    } else {
+        serde_json::from_value::<CircuitConfigWrapper>(value)
+            .map(CircuitConfig::from)
+            .map_err(|err| format!("invalid device description: {}", err))
     }
 }
 
@@ -78,6 +121,23 @@ mod tests {
         assert_eq!(json, serde_json::to_value(&table_data).unwrap());
     }
 
+    #[test]
+    fn global_term() {
+        // a global term drives no pin, so it doesn't need an output pin
+        let json = json!({
+          "dff": false,
+          "global": "ar",
+          "inputPins": [2],
+          "table": [false, true]
+        });
+
+        let table_data: TableData = serde_json::from_str::<TableDataWrapper>(&json.to_string())
+            .unwrap()
+            .into();
+        assert_eq!(table_data.global, Some(GlobalTerm::AsynchronousReset));
+        assert!(table_data.valid(&CircuitConfig::gal22v10()).is_ok());
+    }
+
     #[test]
     fn json_conversion_arr() {
         let json = json!({
@@ -129,28 +189,20 @@ mod tests {
                 .try_into()
                 .unwrap();
 
-        assert_eq!(
-            circuit_config,
-            CircuitConfig {
-                inputs: vec![
-                    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23
-                ],
-                num_fuses: 5892,
-                num_pins: 24,
-                outputs: vec![
-                    (14, 8),
-                    (15, 10),
-                    (16, 12),
-                    (17, 14),
-                    (18, 16),
-                    (19, 16),
-                    (20, 14),
-                    (21, 12),
-                    (22, 10),
-                    (23, 8)
-                ],
-                special_pins: vec![(13, 42)]
-            }
-        );
+        assert_eq!(circuit_config, CircuitConfig::gal22v10());
+    }
+    #[test]
+    fn load_description() {
+        let json = include_str!("../../core-lib/Configs/g16v8.json");
+        assert_eq!(load_config(json), Ok(CircuitConfig::gal16v8()));
+
+        let legacy = json!({
+            "InputPins": [2, 3],
+            "NumFuses": 100,
+            "TotalNumPins": 4,
+            "OutputPins": [[4, 1]],
+            "SpecialPins": []
+        });
+        assert!(load_config(&legacy.to_string()).is_ok());
     }
 }
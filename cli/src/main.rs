@@ -1,11 +1,15 @@
 mod json_load;
+mod render;
 
-use clap::{Parser, Subcommand};
-use json_load::{CircuitConfigWrapper, TableDataWrapper};
-use open_gal::{CircuitConfig, TableData};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use json_load::{load_config, OutputEnableWrapper, TableDataWrapper};
+use open_gal::{
+    CircuitConfig, DefaultFuse, Diagnostic, FuseList, Options, PinAssignment, Report, TableData,
+};
 use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(Parser)]
 #[command(
@@ -16,10 +20,83 @@ use std::io::prelude::*;
     subcommand_required = true
 )]
 struct App {
+    /// How errors and warnings are printed, `json` prints one JSON object per line to stdout
+    #[arg(long, global = true, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
+/// How the fuses are written to the JEDEC file.
+#[derive(Args)]
+struct FuseListArgs {
+    /// Writes every fuse, not only the blocks which differ from the default fuse state
+    #[arg(long)]
+    full_fuse_map: bool,
+
+    /// Number of fuses of a `*L` line
+    #[arg(long, default_value_t = 32)]
+    fuse_block_width: usize,
+
+    /// The `*F` default fuse state, `auto` picks the state most fuses have
+    #[arg(long, value_enum, default_value_t = DefaultFuseArg::Zero)]
+    default_fuse: DefaultFuseArg,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DefaultFuseArg {
+    #[value(name = "0")]
+    Zero,
+    #[value(name = "1")]
+    One,
+    Auto,
+}
+
+impl From<FuseListArgs> for FuseList {
+    fn from(args: FuseListArgs) -> Self {
+        FuseList {
+            width: args.fuse_block_width,
+            full: args.full_fuse_map,
+            default_fuse: match args.default_fuse {
+                DefaultFuseArg::Zero => DefaultFuse::Zero,
+                DefaultFuseArg::One => DefaultFuse::One,
+                DefaultFuseArg::Auto => DefaultFuse::Auto,
+            },
+        }
+    }
+}
+
+/// Exit code if the design or an input is wrong.
+const EXIT_USER: i32 = 1;
+/// Exit code if an output can't be written or the compiler itself failed, the same as a panic.
+const EXIT_INTERNAL: i32 = 101;
+
+/// Why a command failed, it decides the exit code.
+enum Failure {
+    /// the design has errors which have been printed already
+    Compile { message: String, errors: usize },
+    /// an input is wrong, e.g. a missing file or an unknown device
+    User(String),
+    /// an output couldn't be written
+    Internal(String),
+}
+
+/// Number of warnings which have been printed, for the JSON summary.
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::User(message)
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Converts the open-gal source code to the intermediate representation table data
@@ -30,7 +107,7 @@ enum Commands {
         /// Is an intermediate representation
         table_data_json: String,
 
-        /// The path to your GAL type JSON file
+        /// The part name (e.g. GAL22V10) or the path to your GAL type JSON file
         gal_type: Option<String>,
     },
 
@@ -42,8 +119,27 @@ enum Commands {
         /// The name of your JEDEC file
         jedec_filename: String,
 
-        /// The path to your GAL type JSON file
+        /// The part name (e.g. GAL22V10) or the path to your GAL type JSON file
         gal_type: String,
+
+        /// User electronic signature, up to 8 characters
+        #[arg(long)]
+        ues: Option<String>,
+
+        /// Enables the power-down pin of Atmel parts
+        #[arg(long)]
+        power_down: bool,
+
+        /// Lets combinatorial outputs use the polarity which needs fewer product terms
+        #[arg(long)]
+        auto_polarity: bool,
+
+        /// Splits outputs with too many product terms across unused OLMCs, only on 22V10 devices
+        #[arg(long)]
+        split_terms: bool,
+
+        #[command(flatten)]
+        fuse_list: FuseListArgs,
     },
 
     /// Converts the open-gal source code to a JEDEC file
@@ -54,56 +150,279 @@ enum Commands {
         /// The name of your JEDEC file
         jedec_filename: String,
 
-        /// The path to your GAL type JSON file
+        /// The part name (e.g. GAL22V10) or the path to your GAL type JSON file
+        gal_type: String,
+
+        /// User electronic signature, up to 8 characters
+        #[arg(long)]
+        ues: Option<String>,
+
+        /// Enables the power-down pin of Atmel parts
+        #[arg(long)]
+        power_down: bool,
+
+        /// Lets combinatorial outputs use the polarity which needs fewer product terms
+        #[arg(long)]
+        auto_polarity: bool,
+
+        /// Splits outputs with too many product terms across unused OLMCs, only on 22V10 devices
+        #[arg(long)]
+        split_terms: bool,
+
+        #[command(flatten)]
+        fuse_list: FuseListArgs,
+    },
+
+    /// Converts a JEDEC file back to open-gal source code
+    Jedec2Code {
+        /// The JEDEC file, e.g. one read from a programmed GAL
+        jedec_filename: String,
+
+        /// The name of the source code file which is written
+        code: String,
+
+        /// The part name (e.g. GAL22V10) or the path to your GAL type JSON file
         gal_type: String,
     },
+
+    /// Lists the built-in devices
+    Devices,
 }
 
-fn main() -> Result<(), String> {
+fn main() {
     let app = App::parse();
+    let format = app.message_format;
 
-    match app.command {
+    let result = match app.command {
         Commands::Code2Td {
             code,
             table_data_json,
             gal_type,
-        } => code2td(&code, &table_data_json, gal_type.as_deref()),
+        } => code2td(&code, &table_data_json, gal_type.as_deref(), format),
 
         Commands::Td2Jedec {
             table_data_json,
             jedec_filename,
             gal_type,
-        } => td2jedec(&table_data_json, &gal_type, &jedec_filename),
+            ues,
+            power_down,
+            auto_polarity,
+            split_terms,
+            fuse_list,
+        } => td2jedec(
+            &table_data_json,
+            &gal_type,
+            &jedec_filename,
+            &options(ues, power_down, auto_polarity, split_terms, fuse_list),
+            format,
+        ),
 
         Commands::Code2Jedec {
             code,
             jedec_filename,
             gal_type,
-        } => code2jedec(&code, &gal_type, &jedec_filename),
+            ues,
+            power_down,
+            auto_polarity,
+            split_terms,
+            fuse_list,
+        } => code2jedec(
+            &code,
+            &gal_type,
+            &jedec_filename,
+            &options(ues, power_down, auto_polarity, split_terms, fuse_list),
+            format,
+        ),
+
+        Commands::Jedec2Code {
+            jedec_filename,
+            code,
+            gal_type,
+        } => jedec2code(&jedec_filename, &code, &gal_type, format),
+
+        Commands::Devices => {
+            devices(format);
+            Ok(Vec::new())
+        }
+    };
+
+    let (artifacts, errors, exit_code) = match &result {
+        Ok(artifacts) => (artifacts.clone(), 0, 0),
+        Err(Failure::Compile { errors, .. }) => (Vec::new(), *errors, EXIT_USER),
+        Err(Failure::User(_)) => (Vec::new(), 1, EXIT_USER),
+        Err(Failure::Internal(_)) => (Vec::new(), 1, EXIT_INTERNAL),
+    };
+
+    match (format, result) {
+        (_, Ok(_)) | (MessageFormat::Json, Err(Failure::Compile { .. })) => (),
+        (MessageFormat::Human, Err(Failure::Compile { message, .. }))
+        | (MessageFormat::Human, Err(Failure::User(message)))
+        | (MessageFormat::Human, Err(Failure::Internal(message))) => {
+            eprintln!("{}", render::error(&message, render::use_color()));
+        }
+        (MessageFormat::Json, Err(Failure::User(message)))
+        | (MessageFormat::Json, Err(Failure::Internal(message))) => {
+            println!("{}", render::json_error(&message));
+        }
+    }
+
+    if format == MessageFormat::Json {
+        let summary = serde_json::json!({
+            "type": "summary",
+            "success": exit_code == 0,
+            "errors": errors,
+            "warnings": WARNINGS.load(Ordering::Relaxed),
+            "exit_code": exit_code,
+            "artifacts": artifacts,
+        });
+        println!("{}", summary);
+    }
+    std::process::exit(exit_code);
+}
+
+fn options(
+    ues: Option<String>,
+    power_down: bool,
+    auto_polarity: bool,
+    split_terms: bool,
+    fuse_list: FuseListArgs,
+) -> Options {
+    Options {
+        ues: ues.map(|ues| ues.into_bytes()).unwrap_or_default(),
+        power_down,
+        auto_polarity,
+        split_terms,
+        fuse_list: fuse_list.into(),
+        ..Options::default()
+    }
+}
+
+/// Looks up a built-in device, names which aren't a built-in device are read as description file.
+fn device(gal_type: &str) -> Result<CircuitConfig, String> {
+    if let Ok(config) = open_gal::devices::get(gal_type) {
+        return Ok(config);
+    }
+
+    let config = fs::read_to_string(gal_type)
+        .map_err(|_| format!("{} is neither a known device nor a readable file", gal_type))?;
+    load_config(&config)
+        .map_err(|err| format!("couldn't read config file {gal_type}. Error: {err}"))
+}
+
+fn devices(format: MessageFormat) {
+    if format == MessageFormat::Json {
+        for device in open_gal::devices::list() {
+            let mut device = serde_json::to_value(device).unwrap();
+            device["type"] = "device".into();
+            println!("{}", device);
+        }
+        return;
+    }
+
+    println!("{:<10} {:>5} {:>6}  MACROCELLS", "DEVICE", "PINS", "FUSES");
+    for device in open_gal::devices::list() {
+        println!(
+            "{:<10} {:>5} {:>6}  {}",
+            device.name, device.num_pins, device.num_fuses, device.macrocells
+        );
+    }
+}
+
+/// Prints every diagnostic of a compile, rendered to stderr or as JSON to stdout, and returns the
+/// failure which ends the program.
+fn report(file: &str, code: &str, diagnostics: Vec<Diagnostic>, format: MessageFormat) -> Failure {
+    let color = render::use_color();
+    for diagnostic in diagnostics.iter() {
+        match format {
+            MessageFormat::Human => eprintln!("{}", render::render(diagnostic, file, code, color)),
+            MessageFormat::Json => println!("{}", render::json(diagnostic, file, code)),
+        }
+    }
+
+    let message = match diagnostics.len() {
+        1 => format!("could not compile {} due to the previous error", file),
+        n => format!("could not compile {} due to {} previous errors", file, n),
+    };
+    Failure::Compile {
+        message,
+        errors: diagnostics.len(),
+    }
+}
+
+/// Prints the warnings of the code, they don't stop the compile.
+fn print_warnings(file: &str, code: &str, config: Option<&CircuitConfig>, format: MessageFormat) {
+    let color = render::use_color();
+    let warnings = open_gal::lint(code, config);
+    for warning in warnings.iter() {
+        match format {
+            MessageFormat::Human => eprintln!("{}", render::render(warning, file, code, color)),
+            MessageFormat::Json => println!("{}", render::json(warning, file, code)),
+        }
+    }
+    WARNINGS.fetch_add(warnings.len(), Ordering::Relaxed);
+}
+
+/// Prints what the fitter changed, e.g. which pins carry split product terms. Only the human format
+/// has a report.
+fn print_report(report: &Report, format: MessageFormat) {
+    if format == MessageFormat::Json {
+        return;
+    }
+
+    for split in report.splits.iter() {
+        println!("{}", split);
+    }
+}
+
+/// Prints the pinout if the fitter chose a pin for at least one signal. The JSON format leaves it out
+/// like the report, the pinout is in the notes of the JEDEC file as well.
+fn print_pinout(pinout: &[PinAssignment], format: MessageFormat) {
+    if format == MessageFormat::Json || pinout.iter().all(|assignment| assignment.fixed) {
+        return;
+    }
+
+    println!("pinout:");
+    for assignment in pinout {
+        if assignment.fixed {
+            println!("  {}", assignment);
+        } else {
+            println!("  {} (fitted)", assignment);
+        }
     }
 }
 
-fn code2td(code_file: &str, td_name: &str, config_file: Option<&str>) -> Result<(), String> {
+fn code2td(
+    code_file: &str,
+    td_name: &str,
+    config_file: Option<&str>,
+    format: MessageFormat,
+) -> Result<Vec<String>, Failure> {
     let mut file = File::open(code_file)
         .map_err(|_| format!("unable to read source code file {}", code_file))?;
     let mut code = String::new();
     file.read_to_string(&mut code)
         .map_err(|err| format!("{err:?}"))?;
 
-    let table_data = open_gal::parse(&code)?;
-
-    if let Some(config_file) = config_file {
-        let config = fs::read_to_string(&config_file)
-            .map_err(|_| format!("unable to read file {}", config_file))?;
-        let config: CircuitConfig = serde_json::from_str::<CircuitConfigWrapper>(&config)
-            .map_err(|err| format!("couldn't read json of file {config_file}. Error: {err:?}",))?
-            .try_into()
-            .map_err(|err| format!("could not map to struct Error: {err:?}"))?;
-
-        for td in table_data.iter() {
-            td.valid(&config)?;
+    let table_data = match config_file {
+        Some(config_file) => {
+            let config = device(config_file)?;
+            let (table_data, pinout) = open_gal::parse_fitted(&code, &config)
+                .map_err(|errors| report(code_file, &code, errors, format))?;
+            print_warnings(code_file, &code, Some(&config), format);
+            print_pinout(&pinout, format);
+
+            for td in table_data.iter() {
+                td.valid(&config)?;
+            }
+            table_data
         }
-    }
+        None => {
+            let table_data = open_gal::parse(&code)
+                .map_err(|errors| report(code_file, &code, errors, format))?;
+            print_warnings(code_file, &code, None, format);
+            table_data
+        }
+    };
 
     let mut tds: Vec<TableDataWrapper> = Vec::new();
     for td in table_data {
@@ -112,30 +431,42 @@ fn code2td(code_file: &str, td_name: &str, config_file: Option<&str>) -> Result<
             input_pins: td.input_pins,
             output_pin: td.output_pin,
             table: td.table,
+            dont_care: td.dont_care,
+            active_low: td.active_low,
+            oe: td.oe.map(|oe| OutputEnableWrapper {
+                input_pins: oe.input_pins,
+                table: oe.table,
+            }),
+            global: td.global,
         };
         tds.push(td);
     }
 
     let json = serde_json::to_string_pretty(&tds)
-        .map_err(|_| format!("unable to covert table data to json"))?;
+        .map_err(|_| Failure::Internal("unable to convert table data to json".to_string()))?;
 
-    fs::write("output.json", json)
-        .map_err(|_| format!("unable write table data file (file name {})", td_name))?;
+    fs::write(td_name, json).map_err(|_| {
+        Failure::Internal(format!(
+            "unable write table data file (file name {})",
+            td_name
+        ))
+    })?;
 
-    return Ok(());
+    return Ok(vec![td_name.to_string()]);
 }
 
-fn td2jedec(td_file: &str, config_file: &str, jedec_name: &str) -> Result<(), String> {
+fn td2jedec(
+    td_file: &str,
+    config_file: &str,
+    jedec_name: &str,
+    options: &Options,
+    format: MessageFormat,
+) -> Result<Vec<String>, Failure> {
     let json_data = fs::read_to_string(td_file).map_err(|err| format!("{err:?}"))?;
     let table_data: Vec<TableDataWrapper> = serde_json::from_str(&json_data)
         .map_err(|_| format!("couldn't read json of file {}", td_file))?;
 
-    let config = fs::read_to_string(&config_file)
-        .map_err(|_| format!("unable to read file {}", config_file))?;
-    let config: CircuitConfig = serde_json::from_str::<CircuitConfigWrapper>(&config)
-        .map_err(|err| format!("couldn't read json of file {config_file}. Error: {err:?}",))?
-        .try_into()
-        .map_err(|err| format!("could not map to struct Error: {err:?}"))?;
+    let config = device(config_file)?;
 
     let mut truth_tables = Vec::new();
 
@@ -144,21 +475,28 @@ fn td2jedec(td_file: &str, config_file: &str, jedec_name: &str) -> Result<(), St
         truth_tables.push(td);
     }
 
-    let jedec = open_gal::to_jedec(&truth_tables, &config, None)?;
+    let (jedec, fitter_report) = open_gal::compile(&truth_tables, &config, None, options)
+        .map_err(|error| report(td_file, "", vec![error], format))?;
+    print_report(&fitter_report, format);
 
-    fs::write(jedec_name, jedec)
-        .map_err(|_| format!("Unable write jedec file (file name {})", jedec_name))?;
+    fs::write(jedec_name, jedec).map_err(|_| {
+        Failure::Internal(format!(
+            "Unable write jedec file (file name {})",
+            jedec_name
+        ))
+    })?;
 
-    return Ok(());
+    return Ok(vec![jedec_name.to_string()]);
 }
 
-fn code2jedec(code_file: &str, config_file: &str, jedec_name: &str) -> Result<(), String> {
-    let config = fs::read_to_string(&config_file)
-        .map_err(|_| format!("unable to read file {}", config_file))?;
-    let config: CircuitConfig = serde_json::from_str::<CircuitConfigWrapper>(&config)
-        .map_err(|err| format!("couldn't read json of file {config_file}. Error: {err:?}",))?
-        .try_into()
-        .map_err(|err| format!("could not map to struct Error: {err:?}"))?;
+fn code2jedec(
+    code_file: &str,
+    config_file: &str,
+    jedec_name: &str,
+    options: &Options,
+    format: MessageFormat,
+) -> Result<Vec<String>, Failure> {
+    let config = device(config_file)?;
 
     let mut file = File::open(code_file)
         .map_err(|_| format!("unable to read source code file {}", code_file))?;
@@ -166,14 +504,50 @@ fn code2jedec(code_file: &str, config_file: &str, jedec_name: &str) -> Result<()
     file.read_to_string(&mut code)
         .map_err(|err| format!("{err:?}"))?;
 
-    let table_data = open_gal::parse(&code)?;
+    let (table_data, pinout) = open_gal::parse_fitted(&code, &config)
+        .map_err(|errors| report(code_file, &code, errors, format))?;
+    print_warnings(code_file, &code, Some(&config), format);
+    print_pinout(&pinout, format);
+
+    // the pinout is only written to the file if the fitter chose a pin
+    let mut options = options.clone();
+    if pinout.iter().any(|assignment| !assignment.fixed) {
+        options.notes = pinout
+            .iter()
+            .map(|assignment| assignment.to_string())
+            .collect();
+    }
 
-    let jedec = open_gal::to_jedec(&table_data, &config, None)?;
+    let (jedec, fitter_report) = open_gal::compile(&table_data, &config, None, &options)
+        .map_err(|error| report(code_file, &code, vec![error], format))?;
+    print_report(&fitter_report, format);
     match fs::write(jedec_name, jedec) {
-        Ok(()) => Ok(()),
-        Err(_) => Err(format!(
+        Ok(()) => Ok(vec![jedec_name.to_string()]),
+        Err(_) => Err(Failure::Internal(format!(
             "Unable write jedec file (file name {})",
             jedec_name
-        )),
+        ))),
     }
 }
+
+fn jedec2code(
+    jedec_file: &str,
+    code_name: &str,
+    config_file: &str,
+    format: MessageFormat,
+) -> Result<Vec<String>, Failure> {
+    let config = device(config_file)?;
+    let jedec = fs::read_to_string(jedec_file)
+        .map_err(|_| format!("unable to read JEDEC file {}", jedec_file))?;
+
+    let code = open_gal::decompile(&jedec, &config)
+        .map_err(|error| report(jedec_file, &jedec, vec![error], format))?;
+
+    fs::write(code_name, code).map_err(|_| {
+        Failure::Internal(format!(
+            "unable write source code file (file name {})",
+            code_name
+        ))
+    })?;
+    Ok(vec![code_name.to_string()])
+}
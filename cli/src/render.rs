@@ -0,0 +1,243 @@
+use open_gal::{Diagnostic, Severity, Span};
+use serde_json::json;
+use std::io::IsTerminal;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Colors are used if stderr is a terminal and `NO_COLOR` isn't set.
+pub fn use_color() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Renders an error which doesn't belong to the code, e.g. a file which can't be read.
+pub fn error(message: &str, color: bool) -> String {
+    if color {
+        format!("{}error{}{}: {}{}", RED, RESET, BOLD, message, RESET)
+    } else {
+        format!("error: {}", message)
+    }
+}
+
+/// Renders a diagnostic like rustc does: the message, the position, every source line with a
+/// span underlined (`^` for the error, `-` for a label) and the notes as help.
+///
+/// ```text
+/// error[E0004]: y is driven more than once
+///  --> design.ogal:5:1
+///   |
+/// 4 | y = a;
+///   | - y is first driven here
+/// 5 | y = b;
+///   | ^
+/// ```
+pub fn render(diagnostic: &Diagnostic, file: &str, code: &str, color: bool) -> String {
+    let paint = |style: &str, text: &str| {
+        if color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+    let severity_style = match diagnostic.severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+    };
+
+    let mut out = format!(
+        "{}{}\n",
+        paint(
+            severity_style,
+            &format!("{}[{}]", diagnostic.severity, diagnostic.code)
+        ),
+        paint(BOLD, &format!(": {}", diagnostic.message))
+    );
+
+    // the primary span has no text, the message is already in the first line
+    let mut marks: Vec<(Span, &str, bool)> = Vec::new();
+    if let Some(span) = diagnostic.span {
+        marks.push((span, "", true));
+    }
+    for label in diagnostic.labels.iter() {
+        marks.push((label.span, &label.message, false));
+    }
+    marks.sort_by_key(|(span, _, _)| (span.line, span.column));
+
+    let width = marks
+        .iter()
+        .map(|(span, _, _)| span.line.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let gutter = paint(BLUE, &format!("{} |", " ".repeat(width)));
+
+    if let Some(span) = diagnostic.span {
+        out += &format!(
+            "{}{} {}:{}:{}\n",
+            " ".repeat(width),
+            paint(BLUE, "-->"),
+            file,
+            span.line,
+            span.column
+        );
+    }
+
+    if !marks.is_empty() {
+        out += &format!("{}\n", gutter);
+    }
+    let mut last_line = 0;
+    for (span, message, primary) in marks.iter() {
+        let (text, start, len) = source_line(code, span);
+        if span.line != last_line {
+            let number = paint(BLUE, &format!("{:>width$} |", span.line, width = width));
+            out += &format!("{} {}\n", number, text);
+            last_line = span.line;
+        }
+
+        let (symbol, style) = if *primary {
+            ("^", severity_style)
+        } else {
+            ("-", BLUE)
+        };
+        let mut underline = symbol.repeat(len.max(1));
+        if !message.is_empty() {
+            underline += &format!(" {}", message);
+        }
+        out += &format!(
+            "{} {}{}\n",
+            gutter,
+            " ".repeat(start),
+            paint(style, &underline)
+        );
+    }
+
+    if !diagnostic.notes.is_empty() && !marks.is_empty() {
+        out += &format!("{}\n", gutter);
+    }
+    for note in diagnostic.notes.iter() {
+        out += &format!(
+            "{}{} {}\n",
+            " ".repeat(width + 1),
+            paint(BLUE, "="),
+            paint(BOLD, &format!("help: {}", note))
+        );
+    }
+
+    out
+}
+
+/// Converts a diagnostic to the object of `--message-format json`. `spans` holds the position of the
+/// error first and then the labels, `suggestions` holds the hints how to fix it.
+pub fn json(diagnostic: &Diagnostic, file: &str, code: &str) -> serde_json::Value {
+    let span = |span: &Span, primary: bool, label: Option<&str>| {
+        json!({
+            "offset": span.offset,
+            "len": span.len,
+            "line": span.line,
+            "column": span.column,
+            "primary": primary,
+            "label": label,
+        })
+    };
+    let mut spans: Vec<serde_json::Value> = diagnostic
+        .span
+        .iter()
+        .map(|primary| span(primary, true, None))
+        .collect();
+    for label in diagnostic.labels.iter() {
+        spans.push(span(&label.span, false, Some(&label.message)));
+    }
+
+    json!({
+        "type": "diagnostic",
+        "severity": diagnostic.severity,
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+        "file": file,
+        "spans": spans,
+        "suggestions": diagnostic.notes,
+        "rendered": render(diagnostic, file, code, false),
+    })
+}
+
+/// Converts an error which doesn't belong to the code to a diagnostic object without code and file.
+pub fn json_error(message: &str) -> serde_json::Value {
+    json!({
+        "type": "diagnostic",
+        "severity": Severity::Error,
+        "code": null,
+        "message": message,
+        "file": null,
+        "spans": [],
+        "suggestions": [],
+        "rendered": error(message, false),
+    })
+}
+
+/// Returns the source line of a span with tabs expanded, the column the span starts at and the
+/// number of characters it covers on this line.
+fn source_line(code: &str, span: &Span) -> (String, usize, usize) {
+    let offset = (span.offset as usize).min(code.len());
+    let line_start = code[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = code[offset..].find('\n').map_or(code.len(), |i| offset + i);
+    let line = code[line_start..line_end].trim_end_matches('\r');
+
+    let expand = |text: &str| text.replace('\t', "    ");
+    let start = expand(&code[line_start..offset]).chars().count();
+    let end = (offset + span.len as usize).min(line_start + line.len());
+    let len = code
+        .get(offset..end)
+        .map_or(0, |text| expand(text).chars().count());
+
+    (expand(line), start, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use open_gal::{diagnostic, Diagnostic, Label, Span};
+
+    #[test]
+    fn render() {
+        let code = "pin 1 = a;\ny = a;\n\ty = !a;\n";
+        let mut error = Diagnostic::error(
+            diagnostic::MULTIPLE_DRIVERS,
+            "y is driven more than once",
+            Some(Span::new(code, 19..20)),
+        );
+        error.labels.push(Label {
+            span: Span::new(code, 11..12),
+            message: "y is first driven here".to_string(),
+        });
+        error.notes.push("remove one of the equations".to_string());
+
+        let expected = "\
+error[E0004]: y is driven more than once
+ --> design.ogal:3:2
+  |
+2 | y = a;
+  | - y is first driven here
+3 |     y = !a;
+  |     ^
+  |
+  = help: remove one of the equations
+";
+        assert_eq!(super::render(&error, "design.ogal", code, false), expected);
+
+        let json = super::json(&error, "design.ogal", code);
+        assert_eq!(json["code"], "E0004");
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["spans"][0]["primary"], true);
+        assert_eq!(json["spans"][1]["line"], 2);
+        assert_eq!(json["spans"][1]["label"], "y is first driven here");
+        assert_eq!(json["suggestions"][0], "remove one of the equations");
+
+        // errors of the later stages have no position
+        let error: Diagnostic = "too many product terms".to_string().into();
+        assert_eq!(
+            super::render(&error, "design.ogal", code, false),
+            "error[E0008]: too many product terms\n"
+        );
+    }
+}
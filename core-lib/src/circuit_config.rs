@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+/// CircuitConfig describes a device. Besides the pin lists it contains the fuse layout, so a new
+/// device of a known family only needs a new description file (see the files in `Configs`).
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct CircuitConfig {
     pub num_fuses: u32,
@@ -7,9 +9,81 @@ pub struct CircuitConfig {
     pub inputs: Vec<u32>,
     pub outputs: Vec<(u32, u32)>,
     pub special_pins: Vec<(u32, u32)>,
+    pub layout: Layout,
+    /// first of the 64 fuses of the user electronic signature
+    #[serde(default)]
+    pub ues_fuse: Option<u32>,
+    /// index of the power-down enable fuse, only Atmel parts have one
+    #[serde(default)]
+    pub power_down_fuse: Option<u32>,
+}
+
+/// Layout tells the translator where the fuses of a device are. The family decides how the layout
+/// is interpreted.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "family")]
+pub enum Layout {
+    #[serde(rename = "22V10")]
+    Gal22V10(Gal22V10Layout),
+    #[serde(rename = "V8")]
+    V8(V8Layout),
+}
+
+/// Fuse layout of the 22V10 family. Every OLMC has an OE row followed by its product terms and
+/// selects its mode with a S0/S1 fuse pair.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Gal22V10Layout {
+    pub num_columns: u32,
+    /// pin and column of every signal which drives the AND array, the column holds the non inverted
+    /// signal and the next column the inverted one. The column of an output pin is the OLMC feedback.
+    pub columns: Vec<(u32, u32)>,
+    pub olmcs: Vec<Gal22V10Olmc>,
+    /// first fuse of the asynchronous reset row
+    pub ar: u32,
+    /// first fuse of the synchronous preset row
+    pub sp: u32,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Gal22V10Olmc {
+    pub pin: u32,
+    /// first fuse of the OE row, the rows of the product terms follow
+    pub first_fuse: u32,
+    pub s0: u32,
+    pub s1: u32,
+}
+
+/// Fuse layout of the GAL16V8 and GAL20V8. Every OLMC owns eight rows of the AND array, the global
+/// SYN and AC0 fuses select between simple, complex and registered mode.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct V8Layout {
+    pub num_columns: u32,
+    /// output pins in the order of their rows, the first OLMC owns the first eight rows
+    pub olmc_pins: Vec<u32>,
+    /// first XOR fuse, the fuses follow the order of `olmc_pins`
+    pub xor: u32,
+    /// first AC1 fuse, the fuses follow the order of `olmc_pins`
+    pub ac1: u32,
+    /// first product term disable fuse, there is one per row
+    pub ptd: u32,
+    pub syn: u32,
+    pub ac0: u32,
+    /// clock input in registered mode
+    pub clock_pin: u32,
+    /// output enable input in registered mode
+    pub oe_pin: u32,
+    /// pin and column of every pin which can be used as an input in simple mode
+    pub simple: Vec<(u32, u32)>,
+    /// pin and column of every pin which can be used as an input in complex mode
+    pub complex: Vec<(u32, u32)>,
+    /// pin and column of every pin which can be used as an input in registered mode
+    pub registered: Vec<(u32, u32)>,
 }
 
 impl CircuitConfig {
+    /// Creates the config of a 22V10 style device. The fuse layout is derived from the pin lists:
+    /// the AR row comes first, followed by the OLMCs from the highest to the lowest output pin, the
+    /// SP row, the S0/S1 pairs and the UES.
     pub fn new(
         num_fuses: u32,
         num_pins: u32,
@@ -17,12 +91,242 @@ impl CircuitConfig {
         outputs: Vec<(u32, u32)>,
         special_pins: Vec<(u32, u32)>,
     ) -> Self {
+        let num_columns = ((inputs.len() + special_pins.len()) * 2) as u32;
+
+        let mut columns = special_pins.clone();
+        for &pin in inputs.iter() {
+            let column = match outputs.iter().position(|&(out, _)| out == pin) {
+                Some(i) => 2 + (outputs.len() - 1 - i) as u32 * 4,
+                None => (pin - 1) * 4,
+            };
+            columns.push((pin, column));
+        }
+
+        let mut first_fuse = num_columns;
+        let mut olmcs = Vec::new();
+        for &(pin, terms) in outputs.iter().rev() {
+            olmcs.push(Gal22V10Olmc {
+                pin,
+                first_fuse,
+                s0: 0,
+                s1: 0,
+            });
+            first_fuse += (terms + 1) * num_columns;
+        }
+
+        let sp = first_fuse;
+        for (i, olmc) in olmcs.iter_mut().enumerate() {
+            olmc.s0 = sp + num_columns + i as u32 * 2;
+            olmc.s1 = olmc.s0 + 1;
+        }
+        let ues = sp + num_columns + outputs.len() as u32 * 2;
+
         Self {
             num_fuses,
             num_pins,
             inputs,
             outputs,
             special_pins,
+            layout: Layout::Gal22V10(Gal22V10Layout {
+                num_columns,
+                columns,
+                olmcs,
+                ar: 0,
+                sp,
+            }),
+            ues_fuse: if ues + 64 <= num_fuses {
+                Some(ues)
+            } else {
+                None
+            },
+            power_down_fuse: None,
+        }
+    }
+
+    /// Reads a device description and checks that the layout fits the fuse map and the pin lists.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config: Self = serde_json::from_str(json)
+            .map_err(|err| format!("invalid device description: {}", err))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let fuse = |name: &str, index: u32| {
+            if index < self.num_fuses {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} fuse {} is outside of the {} fuses",
+                    name, index, self.num_fuses
+                ))
+            }
+        };
+
+        if let Some(ues) = self.ues_fuse {
+            fuse("UES", ues + 63)?;
+        }
+        if let Some(power_down) = self.power_down_fuse {
+            fuse("power-down", power_down)?;
+        }
+
+        match &self.layout {
+            Layout::Gal22V10(layout) => {
+                fuse("AR", layout.ar + layout.num_columns - 1)?;
+                fuse("SP", layout.sp + layout.num_columns - 1)?;
+                check_columns(&layout.columns, &self.inputs, layout.num_columns)?;
+
+                for &(pin, terms) in self.outputs.iter() {
+                    let olmc = match layout.olmcs.iter().find(|olmc| olmc.pin == pin) {
+                        Some(olmc) => olmc,
+                        None => return Err(format!("output pin {} has no OLMC", pin)),
+                    };
+                    fuse(
+                        "OLMC",
+                        olmc.first_fuse + (terms + 1) * layout.num_columns - 1,
+                    )?;
+                    fuse("S0", olmc.s0)?;
+                    fuse("S1", olmc.s1)?;
+                }
+            }
+            Layout::V8(layout) => {
+                let num_olmcs = layout.olmc_pins.len() as u32;
+                fuse("AND array", num_olmcs * 8 * layout.num_columns - 1)?;
+                fuse("XOR", layout.xor + num_olmcs - 1)?;
+                fuse("AC1", layout.ac1 + num_olmcs - 1)?;
+                fuse("PTD", layout.ptd + num_olmcs * 8 - 1)?;
+                fuse("SYN", layout.syn)?;
+                fuse("AC0", layout.ac0)?;
+
+                for columns in [&layout.simple, &layout.complex, &layout.registered] {
+                    check_columns(columns, &[], layout.num_columns)?;
+                }
+                for pin in self.inputs.iter() {
+                    if ![&layout.simple, &layout.complex, &layout.registered]
+                        .iter()
+                        .any(|columns| columns.iter().any(|(p, _)| p == pin))
+                    {
+                        return Err(format!("input pin {} has no column", pin));
+                    }
+                }
+                for &(pin, terms) in self.outputs.iter() {
+                    if !layout.olmc_pins.contains(&pin) {
+                        return Err(format!("output pin {} has no OLMC", pin));
+                    } else if terms > 8 {
+                        return Err(format!("output pin {} has more than 8 rows", pin));
+                    }
+                }
+            }
         }
+
+        Ok(())
+    }
+
+    /// Config of a GAL22V10.
+    pub fn gal22v10() -> Self {
+        Self::from_json(include_str!("../Configs/g22v10.json")).unwrap()
+    }
+
+    /// Config of an Atmel ATF22V10C, a GAL22V10 with a power-down enable fuse.
+    pub fn atf22v10c() -> Self {
+        Self::from_json(include_str!("../Configs/atf22v10c.json")).unwrap()
+    }
+
+    /// Config of a GAL16V8. Pin 1 and 11 are inputs in simple and complex mode but become the
+    /// clock and output enable in registered mode.
+    pub fn gal16v8() -> Self {
+        Self::from_json(include_str!("../Configs/g16v8.json")).unwrap()
+    }
+
+    /// Config of an Atmel ATF16V8B, a GAL16V8 with a power-down enable fuse.
+    pub fn atf16v8b() -> Self {
+        Self::from_json(include_str!("../Configs/atf16v8b.json")).unwrap()
+    }
+
+    /// Config of a GAL20V8. Pin 1 and 13 are inputs in simple and complex mode but become the
+    /// clock and output enable in registered mode.
+    pub fn gal20v8() -> Self {
+        Self::from_json(include_str!("../Configs/g20v8.json")).unwrap()
+    }
+}
+
+fn check_columns(columns: &[(u32, u32)], inputs: &[u32], num_columns: u32) -> Result<(), String> {
+    for &(pin, column) in columns {
+        if column + 1 >= num_columns {
+            return Err(format!(
+                "column {} of pin {} is outside of the {} columns",
+                column, pin, num_columns
+            ));
+        }
+    }
+    for pin in inputs {
+        if !columns.iter().any(|(p, _)| p == pin) {
+            return Err(format!("input pin {} has no column", pin));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CircuitConfig, Layout};
+
+    #[test]
+    fn derived_layout() {
+        let derived = CircuitConfig::new(
+            5892,
+            24,
+            vec![
+                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+            ],
+            vec![
+                (14, 8),
+                (15, 10),
+                (16, 12),
+                (17, 14),
+                (18, 16),
+                (19, 16),
+                (20, 14),
+                (21, 12),
+                (22, 10),
+                (23, 8),
+            ],
+            vec![(13, 42)],
+        );
+
+        // the description file has to describe the same fuse map
+        assert_eq!(derived, CircuitConfig::gal22v10());
+
+        let Layout::Gal22V10(layout) = derived.layout else {
+            panic!("expected a 22V10 layout");
+        };
+        assert_eq!(layout.sp, 5764);
+        assert_eq!((layout.olmcs[0].pin, layout.olmcs[0].first_fuse), (23, 44));
+        assert_eq!((layout.olmcs[0].s0, layout.olmcs[0].s1), (5808, 5809));
+        assert_eq!(derived.ues_fuse, Some(5828));
+    }
+
+    #[test]
+    fn descriptions() {
+        for config in [
+            CircuitConfig::gal22v10(),
+            CircuitConfig::atf22v10c(),
+            CircuitConfig::gal16v8(),
+            CircuitConfig::atf16v8b(),
+            CircuitConfig::gal20v8(),
+        ] {
+            assert_eq!(config.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn invalid_description() {
+        let mut config = CircuitConfig::gal16v8();
+        config.num_fuses = 2000;
+        assert!(config.validate().is_err());
+
+        let mut config = CircuitConfig::gal22v10();
+        config.outputs.push((24, 8));
+        assert!(config.validate().is_err());
     }
 }
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::circuit_config::Layout;
+use crate::CircuitConfig;
+
+/// A device of the built-in registry.
+struct Device {
+    name: &'static str,
+    /// other names the device can be looked up by, the lookup ignores the case
+    aliases: &'static [&'static str],
+    config: fn() -> CircuitConfig,
+}
+
+const DEVICES: &[Device] = &[
+    Device {
+        name: "GAL22V10",
+        aliases: &["22v10", "g22v10"],
+        config: CircuitConfig::gal22v10,
+    },
+    Device {
+        name: "ATF22V10C",
+        aliases: &["atf22v10"],
+        config: CircuitConfig::atf22v10c,
+    },
+    Device {
+        name: "GAL16V8",
+        aliases: &["16v8", "g16v8"],
+        config: CircuitConfig::gal16v8,
+    },
+    Device {
+        name: "ATF16V8B",
+        aliases: &["atf16v8"],
+        config: CircuitConfig::atf16v8b,
+    },
+    Device {
+        name: "GAL20V8",
+        aliases: &["20v8", "g20v8"],
+        config: CircuitConfig::gal20v8,
+    },
+];
+
+/// Summary of a device for a device picker.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub num_pins: u32,
+    pub num_fuses: u32,
+    /// e.g. "10 OLMCs with 8 to 16 product terms"
+    pub macrocells: String,
+}
+
+/// Returns the config of a built-in device. The name can be the part name or one of its aliases,
+/// the case doesn't matter.
+pub fn get(name: &str) -> Result<CircuitConfig, String> {
+    find(name).map(|device| (device.config)()).ok_or_else(|| {
+        let names: Vec<&str> = DEVICES.iter().map(|device| device.name).collect();
+        format!(
+            "unknown device {}, the supported devices are {}",
+            name,
+            names.join(", ")
+        )
+    })
+}
+
+/// Lists every built-in device.
+pub fn list() -> Vec<DeviceInfo> {
+    DEVICES
+        .iter()
+        .map(|device| {
+            let config = (device.config)();
+            DeviceInfo {
+                name: device.name.to_string(),
+                aliases: device
+                    .aliases
+                    .iter()
+                    .map(|alias| alias.to_string())
+                    .collect(),
+                num_pins: config.num_pins,
+                num_fuses: config.num_fuses,
+                macrocells: macrocells(&config),
+            }
+        })
+        .collect()
+}
+
+fn find(name: &str) -> Option<&'static Device> {
+    DEVICES.iter().find(|device| {
+        device.name.eq_ignore_ascii_case(name)
+            || device
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(name))
+    })
+}
+
+fn macrocells(config: &CircuitConfig) -> String {
+    let terms = config.outputs.iter().map(|&(_, terms)| terms);
+    let (min, max) = (terms.clone().min().unwrap_or(0), terms.max().unwrap_or(0));
+
+    // in complex and registered mode the first row of a V8 OLMC is the OE term
+    let min = match config.layout {
+        Layout::V8(_) => min.saturating_sub(1),
+        Layout::Gal22V10(_) => min,
+    };
+
+    if min == max {
+        format!("{} OLMCs with {} product terms", config.outputs.len(), max)
+    } else {
+        format!(
+            "{} OLMCs with {} to {} product terms",
+            config.outputs.len(),
+            min,
+            max
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CircuitConfig;
+
+    #[test]
+    fn get() {
+        assert_eq!(super::get("GAL22V10"), Ok(CircuitConfig::gal22v10()));
+        assert_eq!(super::get("22v10"), Ok(CircuitConfig::gal22v10()));
+        assert_eq!(super::get("atf22v10c"), Ok(CircuitConfig::atf22v10c()));
+        assert_eq!(super::get("16V8"), Ok(CircuitConfig::gal16v8()));
+        assert!(super::get("GAL26V12").is_err());
+    }
+
+    #[test]
+    fn list() {
+        let devices = super::list();
+        assert_eq!(devices.len(), super::DEVICES.len());
+
+        let gal22v10 = &devices[0];
+        assert_eq!(gal22v10.name, "GAL22V10");
+        assert_eq!((gal22v10.num_pins, gal22v10.num_fuses), (24, 5892));
+        assert_eq!(gal22v10.macrocells, "10 OLMCs with 8 to 16 product terms");
+
+        let gal16v8 = devices.iter().find(|d| d.name == "GAL16V8").unwrap();
+        assert_eq!(gal16v8.macrocells, "8 OLMCs with 7 to 8 product terms");
+
+        // a device description without outputs
+        let mut empty = CircuitConfig::gal16v8();
+        empty.outputs.clear();
+        assert_eq!(super::macrocells(&empty), "0 OLMCs with 0 product terms");
+
+        // every name has to be unique
+        for device in devices.iter() {
+            for name in device.aliases.iter().chain([&device.name]) {
+                assert_eq!(super::find(name).unwrap().name, device.name);
+            }
+        }
+    }
+}
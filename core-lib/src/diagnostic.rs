@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+
+//	Error codes, every diagnostic has one so tools can match on it without parsing the message.
+/// unexpected token or malformed number
+pub const SYNTAX: &str = "E0001";
+/// a table which is incomplete, contradicts itself or doesn't fit the outputs
+pub const TABLE: &str = "E0002";
+/// a pin which is undefined, assigned twice or doesn't exist on the device
+pub const PIN: &str = "E0003";
+/// a signal with more than one table or equation
+pub const MULTIPLE_DRIVERS: &str = "E0004";
+/// a flip flop or output enable on a signal which is never driven
+pub const UNDRIVEN: &str = "E0005";
+/// a node which is undefined, part of a cycle or used like a pin
+pub const NODE: &str = "E0006";
+/// the fitter found no pin for a signal
+pub const FIT: &str = "E0007";
+/// the design doesn't fit the device, e.g. too many product terms
+pub const DEVICE: &str = "E0008";
+/// a JEDEC file which is malformed or whose checksum is wrong
+pub const JEDEC: &str = "E0009";
+
+//	Warning codes of the lints, every lint can be silenced with `// allow(<name>)`.
+/// `unused_pin`: a pin which is declared but never read or driven
+pub const UNUSED_PIN: &str = "W0001";
+/// `unused_input`: an input of a table or equation which no output depends on
+pub const UNUSED_INPUT: &str = "W0002";
+/// `constant_output`: an output which is always 0 or always 1
+pub const CONSTANT_OUTPUT: &str = "W0003";
+/// `unregistered_dff`: a flip flop on a pin which has none
+pub const UNREGISTERED_DFF: &str = "W0004";
+/// `feedback_loop`: a combinatorial output which reads itself through the feedback
+pub const FEEDBACK_LOOP: &str = "W0005";
+/// `full_fill`: a table with a fill whose rows already cover every input
+pub const FULL_FILL: &str = "W0006";
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Position in the source code. Line and column start at 1, the column counts characters. The
+/// fields are 32 bit to keep results with a diagnostic small.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Span {
+    /// byte offset of the first character
+    pub offset: u32,
+    /// length in bytes
+    pub len: u32,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl Span {
+    pub fn new(code: &str, range: Range<usize>) -> Self {
+        let offset = range.start.min(code.len());
+        let line_start = code[..offset].rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            offset: offset as u32,
+            len: range.end.min(code.len()).saturating_sub(offset) as u32,
+            line: code[..offset].matches('\n').count() as u32 + 1,
+            column: code[line_start..offset].chars().count() as u32 + 1,
+        }
+    }
+}
+
+/// A secondary position which explains the diagnostic, e.g. the first of two definitions.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Error or warning of any stage of the compiler. Stages which don't work on the source code, like
+/// the fuse generation, have no span.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    /// hints how to fix the problem
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &str, message: &str, span: Option<Span>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: code.to_string(),
+            message: message.to_string(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning(code: &str, message: &str, span: Option<Span>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, message, span)
+        }
+    }
+}
+
+/// The stages after the parser report bare strings, they all mean that the design doesn't fit.
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Self {
+        Self::error(DEVICE, &message, None)
+    }
+}
+
+impl From<Diagnostic> for String {
+    fn from(diagnostic: Diagnostic) -> Self {
+        diagnostic.to_string()
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// One line like `error[E0004]: line 5:9: y is driven more than once`.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: ", self.severity, self.code)?;
+        if let Some(span) = &self.span {
+            write!(f, "line {}:{}: ", span.line, span.column)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn span() {
+        let code = "pin 1 = a;\n  y = ä & b;";
+        let span = super::Span::new(code, 20..21);
+        assert_eq!(
+            (span.offset, span.len, span.line, span.column),
+            (20, 1, 2, 9)
+        );
+
+        // spans at the end of the code are clamped
+        let span = super::Span::new(code, 40..41);
+        assert_eq!((span.offset, span.len), (code.len() as u32, 0));
+    }
+
+    #[test]
+    fn display() {
+        let span = super::Span::new("a\nbc", 3..4);
+        let diagnostic =
+            super::Diagnostic::error(super::MULTIPLE_DRIVERS, "c is driven twice", Some(span));
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[E0004]: line 2:2: c is driven twice"
+        );
+
+        let diagnostic: super::Diagnostic = "too many terms".to_string().into();
+        assert_eq!(diagnostic.to_string(), "error[E0008]: too many terms");
+    }
+}
@@ -1,21 +1,43 @@
 mod circuit_config;
+pub mod devices;
+pub mod diagnostic;
 mod parser;
 mod table_data;
 mod translator;
 mod transpiler;
 
-pub use circuit_config::CircuitConfig;
-pub use table_data::TableData;
-pub use translator::core::to_jedec;
+pub use circuit_config::{CircuitConfig, Gal22V10Layout, Gal22V10Olmc, Layout, V8Layout};
+pub use diagnostic::{Diagnostic, Label, Severity, Span};
+pub use table_data::{GlobalTerm, OutputEnable, TableData};
+pub use translator::core::{compile, to_jedec, Options};
+pub use translator::decompile::decompile;
+pub use translator::fitter::{PinAssignment, Report, Split};
+pub use translator::jedec::{self, DefaultFuse, FuseList};
+pub use translator::minimize::Effort;
+pub use translator::simulator::Simulator;
 
 pub use parser::OGal;
 pub use transpiler::wincupl::to_wincupl;
 
-pub fn parse(code: &str) -> Result<Vec<TableData>, String> {
-    match parser::parse(code) {
-        Err(error) => Err(format!("{:?}", error)),
-        Ok(td_vec) => Ok(td_vec),
-    }
+/// Parses the code into table data. On failure every error of the code is returned, not only the
+/// first one.
+pub fn parse(code: &str) -> Result<Vec<TableData>, Vec<Diagnostic>> {
+    parser::parse(code)
+}
+
+/// Parses the code for a device, signals without a pin statement get a pin from the fitter. Returns
+/// the table data and the whole pinout.
+pub fn parse_fitted(
+    code: &str,
+    config: &CircuitConfig,
+) -> Result<(Vec<TableData>, Vec<PinAssignment>), Vec<Diagnostic>> {
+    parser::parse_fitted(code, config)
+}
+
+/// Returns the warnings of the code, things which compile but are most likely a mistake. Pass the
+/// device to also check the `.dff`s. A warning is silenced by `// allow(<name>)` on its line.
+pub fn lint(code: &str, config: Option<&CircuitConfig>) -> Vec<Diagnostic> {
+    parser::lint(code, config)
 }
 
 // when parsing pin the number comes first
@@ -0,0 +1,127 @@
+use super::tokens::find_name;
+use super::{Locations, OGal};
+use crate::diagnostic::{self, Diagnostic, Label, Span};
+use std::collections::HashMap;
+
+/// Checks that every name is driven by only one table or equation, has at most one output enable and
+/// that every flip flop belongs to a driven name. A pin can only have one name. Returns an error for
+/// every name which breaks a rule, it points to the statement which breaks it and has a label on the
+/// first one.
+pub fn drivers(
+    code: &str,
+    locations: &Locations,
+    pins: &HashMap<String, usize>,
+) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    let mut push = |code_id: &str, msg: String, offset: usize, label: String, first: usize| {
+        let mut error = Diagnostic::error(code_id, &msg, Some(statement(code, offset)));
+        error.labels.push(Label {
+            span: statement(code, first),
+            message: label,
+        });
+        errors.push(error);
+    };
+
+    // every further driver is reported against the first one
+    for (i, (name, offset)) in locations.drivers.iter().enumerate() {
+        let drivers = &locations.drivers[..i];
+        if let Some((_, first)) = drivers.iter().find(|(other, _)| other == name) {
+            push(
+                diagnostic::MULTIPLE_DRIVERS,
+                format!("{} is driven more than once", name),
+                *offset,
+                format!("{} is first driven here", name),
+                *first,
+            );
+        }
+    }
+
+    for (i, (name, offset)) in locations.output_enables.iter().enumerate() {
+        let enables = &locations.output_enables[..i];
+        if let Some((_, first)) = enables.iter().find(|(other, _)| other == name) {
+            push(
+                diagnostic::MULTIPLE_DRIVERS,
+                format!("{} has more than one output enable", name),
+                *offset,
+                format!("the first output enable of {} is here", name),
+                *first,
+            );
+        }
+    }
+
+    for (i, (name, offset)) in locations.pins.iter().enumerate() {
+        let num = match pins.get(name) {
+            Some(num) => num,
+            None => continue,
+        };
+        let earlier = &locations.pins[..i];
+        if let Some((other, first)) = earlier
+            .iter()
+            .find(|(other, _)| pins.get(other) == Some(num))
+        {
+            push(
+                diagnostic::PIN,
+                format!("pin {} is assigned to {} and {}", num, other, name),
+                *offset,
+                format!("pin {} is first assigned to {} here", num, other),
+                *first,
+            );
+        }
+    }
+
+    for (name, offset) in locations.flip_flops.iter() {
+        if !locations.drivers.iter().any(|(other, _)| other == name) {
+            let mut error = Diagnostic::error(
+                diagnostic::UNDRIVEN,
+                &format!("{} has a flip flop but is never driven", name),
+                Some(statement(code, *offset)),
+            );
+            error
+                .notes
+                .push(format!("add a table or an equation for {}", name));
+            errors.push(error);
+        }
+    }
+
+    errors
+}
+
+/// Checks that every name which is read or driven has a pin, every undefined name is reported once.
+/// Nodes are left out, they are only left in the tables if they have errors of their own.
+pub fn defined(code: &str, o_gal: &OGal, locations: &Locations) -> Vec<Diagnostic> {
+    let mut names: Vec<String> = Vec::new();
+    for table in o_gal.lut.iter().chain(o_gal.oe.iter()) {
+        names.extend(table.in_names());
+        names.extend(table.out_names());
+    }
+    // the output names of the global terms are `ar` and `sp`
+    for (_, table) in o_gal.global.iter() {
+        names.extend(table.in_names());
+    }
+
+    let mut errors = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let node = locations.nodes.iter().any(|(node, _)| node == name);
+        if o_gal.pins.contains_key(name) || names[..i].contains(name) || node {
+            continue;
+        }
+        let mut error = Diagnostic::error(
+            diagnostic::PIN,
+            &format!("pin {} not defined", name),
+            find_name(code, name),
+        );
+        error.notes.push(format!("add `pin <number> = {};`", name));
+        errors.push(error);
+    }
+
+    errors
+}
+
+/// Returns the span of the first token of a statement.
+pub fn statement(code: &str, offset: usize) -> Span {
+    let len = code[offset..]
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(code.len() - offset)
+        .max(1);
+    Span::new(code, offset..offset + len)
+}
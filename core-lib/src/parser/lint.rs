@@ -0,0 +1,282 @@
+use super::check::statement;
+use super::table::Table;
+use super::tokens::find_name;
+use super::{Locations, OGal};
+use crate::diagnostic::{self, Diagnostic, Span};
+use crate::CircuitConfig;
+
+/// Checks the code for things which compile but are most likely a mistake. Returns nothing if the
+/// code doesn't parse, the errors are reported by `parse`. The `.dff` check needs the device, it is
+/// skipped without a config.
+///
+/// A warning is silenced by `// allow(<name>)` on its line or on a comment line right above it.
+pub fn lint(code: &str, config: Option<&CircuitConfig>) -> Vec<Diagnostic> {
+    let (o_gal, locations) = match OGal::parse_located(code) {
+        Ok(parsed) => parsed,
+        Err(_) => return Vec::new(),
+    };
+    let driver = |name: &str| {
+        locations
+            .drivers
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, offset)| statement(code, *offset))
+    };
+
+    let mut warnings = Vec::new();
+    unused_pins(code, &o_gal, &locations, &mut warnings);
+    for table in o_gal.lut.iter() {
+        let span = driver(&table.out_names()[0]);
+        unused_inputs(table, span, &mut warnings);
+        constant_outputs(table, span, &mut warnings);
+    }
+    if let Some(config) = config {
+        unregistered_dffs(code, &o_gal, &locations, config, &mut warnings);
+    }
+    for table in o_gal.lut.iter() {
+        for name in table.out_names() {
+            if !o_gal.dff.contains(&name) && reads_itself(&o_gal, &name) {
+                warn(
+                    &mut warnings,
+                    diagnostic::FEEDBACK_LOOP,
+                    &format!("{} reads itself without a flip flop in between", name),
+                    driver(&name),
+                    "feedback_loop",
+                );
+            }
+        }
+    }
+    for &offset in locations.full_fills.iter() {
+        warn(
+            &mut warnings,
+            diagnostic::FULL_FILL,
+            "the rows of the table already cover every input, the fill is never used",
+            Some(statement(code, offset)),
+            "full_fill",
+        );
+    }
+
+    let mut warnings: Vec<Diagnostic> = warnings
+        .into_iter()
+        .filter(|(name, warning)| !allowed(code, name, warning.span))
+        .map(|(_, warning)| warning)
+        .collect();
+    warnings.sort_by_key(|warning| warning.span.map(|span| (span.line, span.column)));
+    warnings
+}
+
+/// Adds a warning together with the name of its lint.
+fn warn(
+    warnings: &mut Vec<(&'static str, Diagnostic)>,
+    code: &str,
+    msg: &str,
+    span: Option<Span>,
+    name: &'static str,
+) {
+    let mut warning = Diagnostic::warning(code, msg, span);
+    warning
+        .notes
+        .push(format!("silence it with `// allow({})`", name));
+    warnings.push((name, warning));
+}
+
+/// Pins which are neither read nor driven.
+fn unused_pins(
+    code: &str,
+    o_gal: &OGal,
+    locations: &Locations,
+    warnings: &mut Vec<(&'static str, Diagnostic)>,
+) {
+    let mut used: Vec<String> = o_gal.dff.clone();
+    for table in o_gal.lut.iter().chain(o_gal.oe.iter()) {
+        used.extend(table.in_names());
+        used.extend(table.out_names());
+    }
+    for (_, table) in o_gal.global.iter() {
+        used.extend(table.in_names());
+    }
+
+    for (name, offset) in locations.pins.iter() {
+        if !used.contains(name) {
+            warn(
+                warnings,
+                diagnostic::UNUSED_PIN,
+                &format!("pin {} is never used", name),
+                find_name(code, name).or(Some(statement(code, *offset))),
+                "unused_pin",
+            );
+        }
+    }
+}
+
+/// Inputs of a table which none of its outputs depend on. Rows which are don't care depend on
+/// nothing.
+fn unused_inputs(
+    table: &Table,
+    span: Option<Span>,
+    warnings: &mut Vec<(&'static str, Diagnostic)>,
+) {
+    let in_names = table.in_names();
+    let out_names = table.out_names();
+    let outputs = table.get_table();
+    let dont_care: Vec<Vec<bool>> = outputs
+        .iter()
+        .zip(table.dont_care())
+        .map(|(rows, dont_care)| dont_care_mask(rows.len(), &dont_care))
+        .collect();
+
+    // a constant output is reported on its own
+    if (0..outputs.len()).all(|i| constant(&outputs[i], &dont_care[i]).is_some()) {
+        return;
+    }
+
+    for (i, input) in in_names.iter().enumerate() {
+        let bit = 1 << (in_names.len() - 1 - i);
+        let depends = outputs.iter().zip(dont_care.iter()).any(|(rows, dc)| {
+            (0..rows.len()).filter(|row| row & bit == 0).any(|row| {
+                let other = row | bit;
+                !dc[row] && !dc[other] && rows[row] != rows[other]
+            })
+        });
+        if !depends {
+            warn(
+                warnings,
+                diagnostic::UNUSED_INPUT,
+                &format!(
+                    "the function of {} doesn't depend on {}",
+                    out_names.join(", "),
+                    input
+                ),
+                span,
+                "unused_input",
+            );
+        }
+    }
+}
+
+/// Outputs which have the same value for every row which isn't don't care.
+fn constant_outputs(
+    table: &Table,
+    span: Option<Span>,
+    warnings: &mut Vec<(&'static str, Diagnostic)>,
+) {
+    let dont_care = table.dont_care();
+    for ((name, rows), dont_care) in table
+        .out_names()
+        .iter()
+        .zip(table.get_table())
+        .zip(dont_care)
+    {
+        if let Some(value) = constant(&rows, &dont_care_mask(rows.len(), &dont_care)) {
+            warn(
+                warnings,
+                diagnostic::CONSTANT_OUTPUT,
+                &format!("{} is always {}", name, value as u8),
+                span,
+                "constant_output",
+            );
+        }
+    }
+}
+
+/// Marks the rows of an output which are don't care, so a row can be looked up without searching
+/// the list of a wide table.
+fn dont_care_mask(rows: usize, dont_care: &[usize]) -> Vec<bool> {
+    let mut mask = vec![false; rows];
+    for &row in dont_care.iter() {
+        mask[row] = true;
+    }
+    mask
+}
+
+/// Returns the value of an output which is the same for every row, rows which are don't care are
+/// skipped.
+fn constant(rows: &[bool], dont_care: &[bool]) -> Option<bool> {
+    let mut values = (0..rows.len())
+        .filter(|&row| !dont_care[row])
+        .map(|row| rows[row]);
+    let first = values.next()?;
+    values.all(|value| value == first).then_some(first)
+}
+
+/// Flip flops on a pin which isn't an output of the device.
+fn unregistered_dffs(
+    code: &str,
+    o_gal: &OGal,
+    locations: &Locations,
+    config: &CircuitConfig,
+    warnings: &mut Vec<(&'static str, Diagnostic)>,
+) {
+    for (name, offset) in locations.flip_flops.iter() {
+        let pin = match o_gal.pins.get(name) {
+            Some(&pin) => pin as u32,
+            None => continue,
+        };
+        if !config.outputs.iter().any(|&(output, _)| output == pin) {
+            warn(
+                warnings,
+                diagnostic::UNREGISTERED_DFF,
+                &format!(
+                    "pin {} of {} has no flip flop, the .dff has no effect",
+                    pin, name
+                ),
+                Some(statement(code, *offset)),
+                "unregistered_dff",
+            );
+        }
+    }
+}
+
+/// Checks if a combinatorial output reads its own value, directly or through other combinatorial
+/// outputs. A registered output reads the value of the last clock, which is fine.
+fn reads_itself(o_gal: &OGal, name: &str) -> bool {
+    let inputs = |output: &str| -> Vec<String> {
+        o_gal
+            .lut
+            .iter()
+            .filter(|table| table.out_names().iter().any(|out| out == output))
+            .flat_map(|table| table.in_names())
+            .collect()
+    };
+
+    let mut visited: Vec<String> = Vec::new();
+    let mut todo = inputs(name);
+    while let Some(next) = todo.pop() {
+        if next == name {
+            return true;
+        }
+        if visited.contains(&next) || o_gal.dff.contains(&next) {
+            continue;
+        }
+        todo.extend(inputs(&next));
+        visited.push(next);
+    }
+    false
+}
+
+/// Checks if the line of a warning, or the comment line right above it, has an `allow` with the
+/// name of the lint, e.g. `// allow(unused_pin, constant_output)`.
+fn allowed(code: &str, name: &str, span: Option<Span>) -> bool {
+    let line = match span {
+        Some(span) => span.line as usize,
+        None => return false,
+    };
+
+    let lines: Vec<&str> = code.lines().collect();
+    let allows = |text: &str| {
+        text.split_once("//").is_some_and(|(_, comment)| {
+            comment
+                .split("allow(")
+                .skip(1)
+                .filter_map(|rest| rest.split_once(')'))
+                .any(|(names, _)| names.split(',').any(|other| other.trim() == name))
+        })
+    };
+
+    let own = lines.get(line - 1).is_some_and(|text| allows(text));
+    let above = line >= 2
+        && lines
+            .get(line - 2)
+            .is_some_and(|text| text.trim_start().starts_with("//") && allows(text));
+    own || above
+}
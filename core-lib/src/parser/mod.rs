@@ -1,20 +1,77 @@
+mod check;
+mod lint;
+mod nodes;
+mod table;
 mod test;
+mod tokens;
 
-use crate::TableData;
+use crate::diagnostic::{self, Diagnostic};
+use crate::translator::fitter::{self, PinAssignment, Signal};
+use crate::translator::minimize::{minimize, Effort};
+use crate::{CircuitConfig, GlobalTerm, OutputEnable, TableData};
 use hardware_sim::LookupTable;
-use logos::Logos;
 use std::collections::HashMap;
-use tokenizer::{Error, Tokenizer, TypeEq};
-
-pub fn parse(code: &str) -> Result<Vec<TableData>, Error> {
-    let o_gal = OGal::parse(code)?;
+use table::Table;
+use tokenizer::TypeEq;
+use tokens::Tokens;
+
+pub use lint::lint;
+
+/// Parses the code and returns every error of it. The semantic checks also run on the statements which
+/// could be read if others have syntax errors.
+pub fn parse(code: &str) -> Result<Vec<TableData>, Vec<Diagnostic>> {
+    let (o_gal, locations, mut errors) = OGal::parse_recovered(code);
+    errors.extend(check::defined(code, &o_gal, &locations));
+    if !errors.is_empty() {
+        return Err(errors);
+    }
     ogal2td(o_gal)
 }
+
+/// Like `parse` but signals without a pin statement get a pin from the fitter. Returns the table data
+/// and the whole pinout.
+pub fn parse_fitted(
+    code: &str,
+    config: &CircuitConfig,
+) -> Result<(Vec<TableData>, Vec<PinAssignment>), Vec<Diagnostic>> {
+    // the fitter gives every name a pin, so there is nothing left to check on code with errors
+    let (mut o_gal, locations, errors) = OGal::parse_recovered(code);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let pinout = o_gal.assign_pins(config).map_err(|error| vec![error])?;
+    let errors = check::defined(code, &o_gal, &locations);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let td_vec = ogal2td(o_gal)?;
+    Ok((td_vec, pinout))
+}
+
+/// Byte offsets of the statements which declare, drive or register a name, the lints and semantic
+/// checks point to them.
+#[derive(Debug, Clone, Default)]
+struct Locations {
+    pins: Vec<(String, usize)>,
+    drivers: Vec<(String, usize)>,
+    output_enables: Vec<(String, usize)>,
+    flip_flops: Vec<(String, usize)>,
+    nodes: Vec<(String, usize)>,
+    /// tables with a fill whose rows already cover every input
+    full_fills: Vec<usize>,
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct OGal {
     pins: HashMap<String, usize>,
-    lut: Vec<LookupTable>,
+    lut: Vec<Table>,
     dff: Vec<String>,
+    // signals which are the complement of the level of their pin
+    active_low: Vec<String>,
+    // output enable functions, the output names of a lookup table are the outputs it enables
+    oe: Vec<Table>,
+    // functions of the global product terms
+    global: Vec<(GlobalTerm, Table)>,
 }
 
 impl OGal {
@@ -26,69 +83,216 @@ impl OGal {
 
         Self {
             pins: pin_map,
-            lut,
+            lut: lut.into_iter().map(Table::from).collect(),
             dff: dff.iter().map(|&s| s.to_string()).collect(),
+            active_low: Vec::new(),
+            oe: Vec::new(),
+            global: Vec::new(),
+        }
+    }
+
+    /// Parses the code and returns every error of it. After an error the parser skips to the end of
+    /// the statement, which is the next `;` or `}`, and goes on with the next one.
+    pub fn parse(code: &str) -> Result<Self, Vec<Diagnostic>> {
+        Self::parse_located(code).map(|(o_gal, _)| o_gal)
+    }
+
+    /// Like `parse` but also returns where the statements are.
+    fn parse_located(code: &str) -> Result<(Self, Locations), Vec<Diagnostic>> {
+        match Self::parse_recovered(code) {
+            (o_gal, locations, errors) if errors.is_empty() => Ok((o_gal, locations)),
+            (_, _, errors) => Err(errors),
         }
     }
 
-    pub fn parse(code: &str) -> Result<Self, Error> {
+    /// Returns the statements which could be read, where they are and every error of the code. The
+    /// nodes are only flattened if they have no errors.
+    fn parse_recovered(code: &str) -> (Self, Locations, Vec<Diagnostic>) {
         let mut pins = HashMap::new();
         let mut lut = Vec::new();
         let mut dff = Vec::new();
+        let mut active_low = Vec::new();
+        let mut oe = Vec::new();
+        let mut global = Vec::new();
+        let mut nodes = Vec::new();
+        let mut locations = Locations::default();
+        let mut errors = Vec::new();
 
-        let mut tokenizer = Tokenizer::new(Token::lexer(code), vec![Token::Ignore((0, None))]);
+        let mut tokenizer = Tokens::new(code);
 
         while let Some(token) = tokenizer.peek() {
-            match token {
-                Token::Pin => {
-                    pin(&mut tokenizer, &mut pins)?;
+            let start = tokenizer.next_span().start;
+            let (num_luts, num_oes, num_dffs) = (lut.len(), oe.len(), dff.len());
+            let num_nodes = nodes.len();
+
+            let result = match token {
+                Token::Pin => pin(&mut tokenizer, &mut pins, &mut active_low),
+                Token::Table => table(&mut tokenizer, &mut lut).map(|full| {
+                    if full {
+                        locations.full_fills.push(start);
+                    }
+                }),
+                // `ar` and `sp` are only keywords at the start of an equation, so they can still
+                // be names
+                Token::Identifier(name)
+                    if (name == "ar" || name == "sp")
+                        && tokenizer.peek_second() == Some(Token::Equals) =>
+                {
+                    global_term(&mut tokenizer, &mut global)
                 }
-                Token::Table => table(&mut tokenizer, &mut lut)?,
-                Token::Identifier(_) => {
-                    let names = pin_name(&mut tokenizer)?;
+                // `node` is only a keyword if a name follows it
+                Token::Identifier(name)
+                    if name == "node"
+                        && matches!(tokenizer.peek_second(), Some(Token::Identifier(_))) =>
+                {
+                    node(&mut tokenizer, &mut nodes)
+                }
+                Token::Identifier(_) => pin_name(&mut tokenizer).and_then(|names| {
                     if tokenizer.next_is(Token::Dot) {
-                        get_dff(names, &mut tokenizer, &mut dff)?;
+                        attribute(names, &mut tokenizer, &mut dff, &mut active_low, &mut oe)
                     } else {
-                        func(names, &mut tokenizer, &mut lut)?;
+                        func(names, &mut tokenizer, &mut lut)
                     }
-                }
+                }),
                 _ => {
                     tokenizer.next();
                     tokenizer.expect_multi(vec![
                         Token::Pin,
                         Token::Table,
                         Token::Identifier(String::new()),
-                    ])?;
-                    unreachable!();
+                    ])
                 }
+            };
+            if let Err(error) = result {
+                errors.push(error);
+                tokenizer.recover();
+            }
+
+            for table in lut[num_luts..].iter() {
+                let names = table.out_names().into_iter().map(|name| (name, start));
+                locations.drivers.extend(names);
             }
+            for table in oe[num_oes..].iter() {
+                let names = table.out_names().into_iter().map(|name| (name, start));
+                locations.output_enables.extend(names);
+            }
+            let names = dff[num_dffs..].iter().map(|name| (name.clone(), start));
+            locations.flip_flops.extend(names);
+            let names = nodes[num_nodes..].iter().map(|name| (name.clone(), start));
+            locations.nodes.extend(names);
+            let mut declared: Vec<&String> = pins
+                .keys()
+                .filter(|name| !locations.pins.iter().any(|(other, _)| other == *name))
+                .collect();
+            declared.sort_by_key(|name| (pins[*name], name.to_string()));
+            for name in declared {
+                locations.pins.push((name.clone(), start));
+            }
+        }
+
+        errors.extend(check::drivers(code, &locations, &pins));
+
+        let mut o_gal = Self {
+            pins,
+            lut,
+            dff,
+            active_low,
+            oe,
+            global,
+        };
+        if let Err(node_errors) = nodes::flatten(code, &mut o_gal, &nodes) {
+            errors.extend(node_errors);
         }
 
-        Ok(Self { pins, lut, dff })
+        (o_gal, locations, errors)
+    }
+
+    /// Lets the fitter choose a pin for every signal without a pin statement. The number of product
+    /// terms of an output is estimated by minimizing its table.
+    pub fn assign_pins(
+        &mut self,
+        config: &CircuitConfig,
+    ) -> Result<Vec<PinAssignment>, Diagnostic> {
+        let mut signals: Vec<Signal> = Vec::new();
+
+        for lut in self.lut.iter() {
+            for (i, name) in lut.out_names().into_iter().enumerate() {
+                if signals.iter().any(|signal| signal.name == name) {
+                    continue;
+                }
+
+                // the terms of an active low output are the ones of the signal as well
+                let table = &lut.get_table()[i];
+                let dont_care = &lut.dont_care()[i];
+                let terms = minimize(lut.in_names().len(), table, dont_care, Effort::default());
+
+                signals.push(Signal {
+                    registered: self.dff.contains(&name),
+                    name,
+                    terms: Some(terms.len()),
+                });
+            }
+        }
+
+        let luts = self
+            .lut
+            .iter()
+            .chain(self.oe.iter())
+            .chain(self.global.iter().map(|(_, lut)| lut));
+        for lut in luts {
+            for name in lut.in_names() {
+                if !signals.iter().any(|signal| signal.name == name) {
+                    signals.push(Signal {
+                        name,
+                        terms: None,
+                        registered: false,
+                    });
+                }
+            }
+        }
+
+        let pinout = fitter::assign_pins(&signals, &self.pins, config)
+            .map_err(|msg| Diagnostic::error(diagnostic::FIT, &msg, None))?;
+        for assignment in pinout.iter() {
+            self.pins
+                .insert(assignment.name.clone(), assignment.pin as usize);
+        }
+
+        Ok(pinout)
     }
 }
 
-fn pin(tokenizer: &mut Tokenizer<Token>, pins: &mut HashMap<String, usize>) -> Result<(), Error> {
+fn pin(
+    tokenizer: &mut Tokens,
+    pins: &mut HashMap<String, usize>,
+    active_low: &mut Vec<String>,
+) -> Result<(), Diagnostic> {
     tokenizer.expect_next(Token::Pin)?;
     let nums = pin_num(tokenizer)?;
     tokenizer.expect_next(Token::Equals)?;
-    let names = pin_name(tokenizer)?;
+    let names = pin_decl_names(tokenizer, active_low)?;
 
     if nums.len() != names.len() {
-        return Err(tokenizer.error(&format!(
-            "pin name len ({}) and pin number len ({}) doesn't match",
-            names.len(),
-            nums.len()
-        )));
+        return Err(tokenizer.error(
+            diagnostic::PIN,
+            &format!(
+                "pin name len ({}) and pin number len ({}) doesn't match",
+                names.len(),
+                nums.len()
+            ),
+        ));
     }
 
     for i in 0..nums.len() {
         if let Some(num) = pins.insert(names[i].clone(), nums[i]) {
-            return Err(tokenizer.error(&format!(
-                "pin {} has been already assigned to {}",
-                names[i].clone(),
-                num
-            )));
+            return Err(tokenizer.error(
+                diagnostic::PIN,
+                &format!(
+                    "pin {} has been already assigned to {}",
+                    names[i].clone(),
+                    num
+                ),
+            ));
         }
     }
 
@@ -97,11 +301,28 @@ fn pin(tokenizer: &mut Tokenizer<Token>, pins: &mut HashMap<String, usize>) -> R
     Ok(())
 }
 
+/// Reads `node name, ...;`, a node is a named function which doesn't need a pin.
+fn node(tokenizer: &mut Tokens, nodes: &mut Vec<String>) -> Result<(), Diagnostic> {
+    tokenizer.expect_next(Token::Identifier(String::new()))?;
+    for name in pin_name(tokenizer)? {
+        if nodes.contains(&name) {
+            return Err(tokenizer.error(
+                diagnostic::NODE,
+                &format!("node {} has been already declared", name),
+            ));
+        }
+        nodes.push(name);
+    }
+    tokenizer.expect_next(Token::Semicolon)?;
+
+    Ok(())
+}
+
 fn func(
     names: Vec<String>,
-    tokenizer: &mut Tokenizer<Token>,
-    lut: &mut Vec<LookupTable>,
-) -> Result<(), Error> {
+    tokenizer: &mut Tokens,
+    lut: &mut Vec<Table>,
+) -> Result<(), Diagnostic> {
     tokenizer.expect_next(Token::Equals)?;
 
     let mut func = Vec::new();
@@ -120,8 +341,10 @@ fn func(
                 } else if num == "1" {
                     bool_algebra::Token::One
                 } else {
-                    return Err(tokenizer
-                        .error(&format!("unexpected char {} only '0' or '1' allowed", num)));
+                    return Err(tokenizer.error(
+                        diagnostic::SYNTAX,
+                        &format!("unexpected char {} only '0' or '1' allowed", num),
+                    ));
                 }
             }
             Token::Semicolon => break,
@@ -133,10 +356,12 @@ fn func(
 
         func.push(bool_token);
     }
+    // the code ended before the `;`
+    tokenizer.expect(Token::Semicolon)?;
 
     let out_table = match bool_algebra::parse(&func) {
         Ok(table) => table,
-        Err(msg) => return Err(tokenizer.error(&msg)),
+        Err(msg) => return Err(tokenizer.error(diagnostic::SYNTAX, &msg)),
     };
 
     let mut table = Vec::new();
@@ -148,28 +373,62 @@ fn func(
     let out_names = names.iter().map(|s| s.as_ref()).collect();
     let lt = LookupTable::new(table, in_names, out_names, "").unwrap();
 
-    lut.push(lt);
+    lut.push(lt.into());
+
+    Ok(())
+}
+
+/// Reads `ar = expr;` or `sp = expr;`, every global term can only be defined once.
+fn global_term(
+    tokenizer: &mut Tokens,
+    global: &mut Vec<(GlobalTerm, Table)>,
+) -> Result<(), Diagnostic> {
+    let (term, name) = match get_name(tokenizer)?.as_str() {
+        "ar" => (GlobalTerm::AsynchronousReset, "ar"),
+        "sp" => (GlobalTerm::SynchronousPreset, "sp"),
+        _ => return Err(tokenizer.unexpected_keyword(&[], &["ar", "sp"])),
+    };
+    if global.iter().any(|(other, _)| *other == term) {
+        return Err(tokenizer.error(
+            diagnostic::MULTIPLE_DRIVERS,
+            &format!("{} has been already defined", name),
+        ));
+    }
+
+    let mut lut = Vec::new();
+    func(vec![name.to_string()], tokenizer, &mut lut)?;
+    global.push((term, lut.remove(0)));
 
     Ok(())
 }
 
-fn get_dff(
+/// Reads `.dff;`, `.invert;` or `.oe = expr;` after a list of names.
+fn attribute(
     names: Vec<String>,
-    tokenizer: &mut Tokenizer<Token>,
+    tokenizer: &mut Tokens,
     dff: &mut Vec<String>,
-) -> Result<(), Error> {
+    active_low: &mut Vec<String>,
+    oe: &mut Vec<Table>,
+) -> Result<(), Diagnostic> {
     tokenizer.expect_next(Token::Dot)?;
-    tokenizer.expect_next(Token::Dff)?;
+    // `invert` and `oe` are only keywords after the `.`, so they can still be names
+    let list = match tokenizer.next() {
+        Some(Token::Dff) => dff,
+        Some(Token::Identifier(name)) if name == "invert" => active_low,
+        Some(Token::Identifier(name)) if name == "oe" => return func(names, tokenizer, oe),
+        _ => return Err(tokenizer.unexpected_keyword(&[Token::Dff], &["invert", "oe"])),
+    };
     tokenizer.expect_next(Token::Semicolon)?;
 
     for name in names {
-        dff.push(name);
+        list.push(name);
     }
 
     Ok(())
 }
 
-fn table(tokenizer: &mut Tokenizer<Token>, lut: &mut Vec<LookupTable>) -> Result<(), Error> {
+/// Reads a table, returns true if it has a fill but its rows already cover every input.
+fn table(tokenizer: &mut Tokens, lut: &mut Vec<Table>) -> Result<bool, Diagnostic> {
     tokenizer.expect_next(Token::Table)?;
     tokenizer.expect_next(Token::RoundOpen)?;
     let in_names = pin_name(tokenizer)?;
@@ -178,7 +437,7 @@ fn table(tokenizer: &mut Tokenizer<Token>, lut: &mut Vec<LookupTable>) -> Result
     tokenizer.expect_next(Token::RoundClose)?;
 
     enum Fill {
-        Fill(bool),
+        Fill(Option<bool>),
         None,
         Count,
     }
@@ -195,23 +454,16 @@ fn table(tokenizer: &mut Tokenizer<Token>, lut: &mut Vec<LookupTable>) -> Result
             tokenizer.expect_next(Token::Fill)?;
             tokenizer.expect_next(Token::RoundOpen)?;
 
-            let num =
-                if let Token::Number(num) = tokenizer.expect_next(Token::Number(String::new()))? {
-                    num
-                } else {
-                    unreachable!()
-                };
+            let values = trit_table(tokenizer)?;
             tokenizer.expect_next(Token::RoundClose)?;
 
-            if num == "0" {
-                fill = Fill::Fill(false);
-            } else if num == "1" {
-                fill = Fill::Fill(true);
-            } else {
-                return Err(
-                    tokenizer.error(&format!("unexpected char {} only '0' or '1' allowed", num))
-                );
+            if values.len() != 1 {
+                return Err(tokenizer.error(
+                    diagnostic::SYNTAX,
+                    "fill expects exactly one value '0', '1' or '-'",
+                ));
             }
+            fill = Fill::Fill(values[0]);
         }
     } else {
         fill = Fill::None;
@@ -219,35 +471,28 @@ fn table(tokenizer: &mut Tokenizer<Token>, lut: &mut Vec<LookupTable>) -> Result
 
     tokenizer.expect_next(Token::CurlyOpen)?;
 
-    let table = bool_table(tokenizer)?;
-
-    let table = match fill {
-        Fill::Count => bool_algebra::parse_count(
-            in_names.len(),
-            out_names.len(),
-            table,
-            crate::COUNT_VERTICAL,
-        ),
-        Fill::Fill(fill) => bool_algebra::parse_fill(in_names.len(), out_names.len(), table, fill),
-        Fill::None => bool_algebra::parse_full(in_names.len(), out_names.len(), table),
-    };
+    let values = trit_table(tokenizer)?;
+    let (num_in, num_out) = (in_names.len(), out_names.len());
+    let full = matches!(fill, Fill::Fill(_)) && table::covers_every_input(num_in, num_out, &values);
 
-    let table = match table {
-        Ok(table) => table,
-        Err(msg) => return Err(tokenizer.error(&msg)),
+    let columns = match fill {
+        Fill::Count => table::parse_count(num_in, num_out, &values),
+        Fill::Fill(fill) => table::parse_rows(num_in, num_out, &values, Some(fill)),
+        Fill::None => table::parse_rows(num_in, num_out, &values, None),
     };
 
-    let in_names = in_names.iter().map(|s| s.as_ref()).collect();
-    let out_names = out_names.iter().map(|s| s.as_ref()).collect();
-    let lt = LookupTable::new(table, in_names, out_names, "").unwrap();
-    lut.push(lt);
+    let columns = match columns {
+        Ok(columns) => columns,
+        Err(msg) => return Err(tokenizer.error(diagnostic::TABLE, &msg)),
+    };
+    lut.push(Table::new(columns, &in_names, &out_names));
 
     tokenizer.expect_next(Token::CurlyClose)?;
 
-    Ok(())
+    Ok(full)
 }
 
-fn pin_num(tokenizer: &mut Tokenizer<Token>) -> Result<Vec<usize>, Error> {
+fn pin_num(tokenizer: &mut Tokens) -> Result<Vec<usize>, Diagnostic> {
     if tokenizer.next_is(Token::SquareOpen) {
         tokenizer.expect_next(Token::SquareOpen)?;
         let start = get_num(tokenizer)?;
@@ -278,18 +523,20 @@ fn pin_num(tokenizer: &mut Tokenizer<Token>) -> Result<Vec<usize>, Error> {
     }
 }
 
-fn get_num(tokenizer: &mut Tokenizer<Token>) -> Result<usize, Error> {
+fn get_num(tokenizer: &mut Tokens) -> Result<usize, Diagnostic> {
     if let Token::Number(num) = tokenizer.expect_next(Token::Number(String::new()))? {
         match num.parse() {
             Ok(num) => Ok(num),
-            Err(err) => Err(tokenizer.error(&format!("ParseIntError: {}", err))),
+            Err(err) => {
+                Err(tokenizer.error(diagnostic::SYNTAX, &format!("ParseIntError: {}", err)))
+            }
         }
     } else {
         unreachable!();
     }
 }
 
-fn pin_name(tokenizer: &mut Tokenizer<Token>) -> Result<Vec<String>, Error> {
+fn pin_name(tokenizer: &mut Tokens) -> Result<Vec<String>, Diagnostic> {
     let name = get_name(tokenizer)?;
     if tokenizer.next_is(Token::SquareOpen) {
         let mut result = Vec::new();
@@ -313,7 +560,43 @@ fn pin_name(tokenizer: &mut Tokenizer<Token>) -> Result<Vec<String>, Error> {
     }
 }
 
-fn get_name(tokenizer: &mut Tokenizer<Token>) -> Result<String, Error> {
+/// Reads the names of a pin declaration. Like `pin_name` but every name or range can start with a
+/// '!' which declares the signal active low, e.g. `pin 1, 23 = !reset, !cs;`.
+fn pin_decl_names(
+    tokenizer: &mut Tokens,
+    active_low: &mut Vec<String>,
+) -> Result<Vec<String>, Diagnostic> {
+    let mut result = Vec::new();
+
+    loop {
+        let inverted = tokenizer.next_is(Token::Not);
+        if inverted {
+            tokenizer.expect_next(Token::Not)?;
+        }
+
+        let name = get_name(tokenizer)?;
+        let names = if tokenizer.next_is(Token::SquareOpen) {
+            pin_num(tokenizer)?
+                .iter()
+                .map(|num| format!("{}{}", name, num))
+                .collect()
+        } else {
+            vec![name]
+        };
+
+        if inverted {
+            active_low.extend(names.iter().cloned());
+        }
+        result.extend(names);
+
+        if !tokenizer.next_is(Token::Comma) {
+            return Ok(result);
+        }
+        tokenizer.expect_next(Token::Comma)?;
+    }
+}
+
+fn get_name(tokenizer: &mut Tokens) -> Result<String, Diagnostic> {
     if let Token::Identifier(name) = tokenizer.expect_next(Token::Identifier(String::new()))? {
         Ok(name)
     } else {
@@ -321,71 +604,187 @@ fn get_name(tokenizer: &mut Tokenizer<Token>) -> Result<String, Error> {
     }
 }
 
-fn bool_table(tokenizer: &mut Tokenizer<Token>) -> Result<Vec<bool>, Error> {
+/// Reads table values until the next token which is not part of a table. Next to '0' and '1' a table
+/// can contain don't care values written as '-' or 'x', they are returned as `None`.
+fn trit_table(tokenizer: &mut Tokens) -> Result<Vec<Option<bool>>, Diagnostic> {
     let mut result = Vec::new();
 
-    while tokenizer.next_is(Token::Number(String::new())) {
-        if let Token::Number(num) = tokenizer.expect_next(Token::Number(String::new()))? {
-            let temp = match get_bool(num) {
-                Ok(temp) => temp,
-                Err(msg) => return Err(tokenizer.error(&msg)),
-            };
-
-            for b in temp {
-                result.push(b);
+    loop {
+        let chars = if tokenizer.next_is(Token::Number(String::new())) {
+            match tokenizer.expect_next(Token::Number(String::new()))? {
+                Token::Number(num) => num,
+                _ => unreachable!(),
+            }
+        } else if tokenizer.next_is(Token::Identifier(String::new())) {
+            match tokenizer.expect_next(Token::Identifier(String::new()))? {
+                Token::Identifier(name) => name,
+                _ => unreachable!(),
             }
+        } else if tokenizer.next_is(Token::Minus) {
+            tokenizer.expect_next(Token::Minus)?;
+            "-".to_string()
         } else {
-            unreachable!();
+            break;
+        };
+
+        match get_trits(&chars) {
+            Ok(trits) => result.extend(trits),
+            Err(msg) => return Err(tokenizer.error(diagnostic::SYNTAX, &msg)),
         }
     }
 
     Ok(result)
 }
 
-fn get_bool(num: String) -> Result<Vec<bool>, String> {
+fn get_trits(chars: &str) -> Result<Vec<Option<bool>>, String> {
     let mut result = Vec::new();
-    for c in num.chars() {
-        if c == '0' {
-            result.push(false);
-        } else if c == '1' {
-            result.push(true);
-        } else {
-            return Err(format!("unexpected char {} only '0' or '1' allowed", c));
+    for c in chars.chars() {
+        match c {
+            '0' => result.push(Some(false)),
+            '1' => result.push(Some(true)),
+            '-' | 'x' | 'X' => result.push(None),
+            _ => {
+                return Err(format!(
+                    "unexpected char {} only '0', '1', '-' or 'x' allowed",
+                    c
+                ))
+            }
         }
     }
 
     Ok(result)
 }
 
-pub fn ogal2td(o_gal: OGal) -> Result<Vec<TableData>, Error> {
-    // use hardware_sim::Component;
+/// Converts the statements to table data, every error is reported.
+pub fn ogal2td(o_gal: OGal) -> Result<Vec<TableData>, Vec<Diagnostic>> {
     let mut td_vec = Vec::with_capacity(o_gal.lut.len());
+    let mut errors = Vec::new();
+    let active_low = lookup_pins(o_gal.active_low.clone(), &o_gal.pins);
+    let dffs = lookup_pins(o_gal.dff.clone(), &o_gal.pins);
+    let (active_low, dffs) = match (active_low, dffs) {
+        (Ok(active_low), Ok(dffs)) => (active_low, dffs),
+        (active_low, dffs) => return Err(active_low.err().into_iter().chain(dffs.err()).collect()),
+    };
 
-    for lut in o_gal.lut {
-        let out_pins = lookup_pins(lut.out_names(), &o_gal.pins)?;
-        let dffs = lookup_pins(o_gal.dff.clone(), &o_gal.pins)?;
+    for lut in o_gal.lut.iter() {
+        let dont_care = lut.dont_care();
+        // a table whose pins aren't defined is skipped, every missing pin is reported
+        let (in_pins, out_pins) = match (
+            lookup_pins(lut.in_names(), &o_gal.pins),
+            lookup_pins(lut.out_names(), &o_gal.pins),
+        ) {
+            (Ok(in_pins), Ok(out_pins)) => (in_pins, out_pins),
+            (in_pins, out_pins) => {
+                errors.extend(in_pins.err().into_iter().chain(out_pins.err()));
+                continue;
+            }
+        };
+        let flip = flipped_inputs(&in_pins, &active_low);
 
         for (i, &out_pin) in out_pins.iter().enumerate() {
-            let td = TableData::new(
-                lookup_pins(lut.in_names(), &o_gal.pins)?,
-                out_pin,
-                lut.get_table()[i].clone(),
-                dffs.contains(&out_pin),
-            );
+            let inverted = active_low.contains(&out_pin);
+            let table = pin_levels(&lut.get_table()[i], flip, inverted);
+
+            let mut td = TableData::new(in_pins.clone(), out_pin, table, dffs.contains(&out_pin));
+            td.active_low = inverted;
+            td.dont_care = dont_care[i].iter().map(|&index| index ^ flip).collect();
+            td.dont_care.sort();
             td_vec.push(td);
         }
     }
 
-    Ok(td_vec)
+    for lut in o_gal.oe.iter() {
+        let (in_pins, out_pins) = match (
+            lookup_pins(lut.in_names(), &o_gal.pins),
+            lookup_pins(lut.out_names(), &o_gal.pins),
+        ) {
+            (Ok(in_pins), Ok(out_pins)) => (in_pins, out_pins),
+            (in_pins, out_pins) => {
+                errors.extend(in_pins.err().into_iter().chain(out_pins.err()));
+                continue;
+            }
+        };
+        let flip = flipped_inputs(&in_pins, &active_low);
+
+        for (i, out_pin) in out_pins.iter().enumerate() {
+            let td = match td_vec.iter_mut().find(|td| td.output_pin == *out_pin) {
+                Some(td) => td,
+                None => {
+                    errors.push(Diagnostic::error(
+                        diagnostic::UNDRIVEN,
+                        &format!(
+                            "{} has an output enable but no function",
+                            lut.out_names()[i]
+                        ),
+                        None,
+                    ));
+                    continue;
+                }
+            };
+            td.oe = Some(OutputEnable {
+                input_pins: in_pins.clone(),
+                table: pin_levels(&lut.get_table()[i], flip, false),
+            });
+        }
+    }
+
+    for (term, lut) in o_gal.global.iter() {
+        let in_pins = match lookup_pins(lut.in_names(), &o_gal.pins) {
+            Ok(in_pins) => in_pins,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+        let flip = flipped_inputs(&in_pins, &active_low);
+
+        // a global term drives no pin, so its output pin is ignored
+        let table = pin_levels(&lut.get_table()[0], flip, false);
+        let mut td = TableData::new(in_pins, 0, table, false);
+        td.global = Some(*term);
+        td_vec.push(td);
+    }
+
+    if errors.is_empty() {
+        Ok(td_vec)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Returns the bits of the table index which belong to active low inputs. The table data holds the
+/// levels of the pins and an active low input is the complement of its pin, so the index of a pin
+/// level is the index of the signal with these bits flipped.
+fn flipped_inputs(in_pins: &[u32], active_low: &[u32]) -> usize {
+    in_pins
+        .iter()
+        .enumerate()
+        .filter(|(_, pin)| active_low.contains(pin))
+        .map(|(j, _)| 1 << (in_pins.len() - 1 - j))
+        .sum()
+}
+
+/// Converts the table of a signal to the levels of the pins.
+fn pin_levels(signal: &[bool], flip: usize, inverted: bool) -> Vec<bool> {
+    (0..signal.len())
+        .map(|index| signal[index ^ flip] != inverted)
+        .collect()
 }
 
-fn lookup_pins(pin_name: Vec<String>, pin_map: &HashMap<String, usize>) -> Result<Vec<u32>, Error> {
+fn lookup_pins(
+    pin_name: Vec<String>,
+    pin_map: &HashMap<String, usize>,
+) -> Result<Vec<u32>, Diagnostic> {
     let mut pin_num = Vec::with_capacity(pin_name.len());
     for name in pin_name {
         if let Some(&num) = pin_map.get(&name) {
             pin_num.push(num as u32);
         } else {
-            return Err(Error::msg(&format!("pin {} not defined", name)));
+            return Err(Diagnostic::error(
+                diagnostic::PIN,
+                &format!("pin {} not defined", name),
+                None,
+            ));
         }
     }
     Ok(pin_num)
@@ -437,6 +836,8 @@ pub enum Token {
 
     #[token("->")]
     Arrow, // ->
+    #[token("-")]
+    Minus, // -
 
     #[regex(r"[a-zA-Z_][a-zA-Z_0-9]+", |lex| lex.slice().parse())]
     #[regex(r"[a-zA-Z]", |lex| lex.slice().parse())]
@@ -0,0 +1,196 @@
+use super::table::Table;
+use super::tokens::find_name;
+use super::OGal;
+use crate::diagnostic::{self, Diagnostic};
+use std::collections::HashMap;
+
+/// Function of a node, the first input is the most significant bit of the table index.
+#[derive(Debug, Clone)]
+struct Node {
+    in_names: Vec<String>,
+    table: Vec<bool>,
+}
+
+/// Substitutes the functions of the buried nodes into every table which reads them, so no node is
+/// left that would need a pin. Don't cares of a node function are treated as 0. Every error is
+/// reported and the tables are only changed if there is none.
+pub fn flatten(code: &str, o_gal: &mut OGal, nodes: &[String]) -> Result<(), Vec<Diagnostic>> {
+    if nodes.is_empty() {
+        return Ok(());
+    }
+    // errors point to the declaration of the node
+    let error =
+        |name: &str, msg: String| Diagnostic::error(diagnostic::NODE, &msg, find_name(code, name));
+    let mut errors = Vec::new();
+
+    for node in nodes {
+        if o_gal.pins.contains_key(node) {
+            errors.push(error(
+                node,
+                format!("{} is a node and can't be assigned to a pin", node),
+            ));
+        }
+        if o_gal.dff.contains(node) {
+            errors.push(error(node, format!("node {} can't have a flip flop", node)));
+        }
+        if o_gal.active_low.contains(node) {
+            errors.push(error(
+                node,
+                format!("node {} has no pin which could be inverted", node),
+            ));
+        }
+        if o_gal.oe.iter().any(|lut| lut.out_names().contains(node)) {
+            errors.push(error(
+                node,
+                format!("node {} has no output which could be enabled", node),
+            ));
+        }
+    }
+
+    // take the node columns out of the tables
+    let mut definitions = HashMap::new();
+    let mut lut = Vec::with_capacity(o_gal.lut.len());
+    for table in o_gal.lut.iter() {
+        let (mut columns, mut out_names) = (Vec::new(), Vec::new());
+        for (name, column) in table.out_names().into_iter().zip(table.columns()) {
+            if !nodes.contains(&name) {
+                columns.push(column.clone());
+                out_names.push(name);
+                continue;
+            }
+
+            let node = Node {
+                in_names: table.in_names(),
+                table: column.iter().map(|value| value.unwrap_or(false)).collect(),
+            };
+            if definitions.insert(name.clone(), node).is_some() {
+                errors.push(error(
+                    &name,
+                    format!("node {} is defined more than once", name),
+                ));
+            }
+        }
+
+        if !out_names.is_empty() {
+            lut.push(Table::new(columns, &table.in_names(), &out_names));
+        }
+    }
+
+    // resolve the nodes which read other nodes first, a node without a function is reported once
+    // even if other nodes read it
+    let mut resolved = HashMap::new();
+    for node in nodes {
+        if let Err(msg) = resolve(node, nodes, &definitions, &mut resolved, &mut Vec::new()) {
+            if !errors.iter().any(|error| error.message == msg) {
+                errors.push(error(node, msg));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    for table in lut.iter_mut().chain(o_gal.oe.iter_mut()) {
+        let (in_names, index_map) = substitute(&table.in_names(), &resolved);
+        let columns = table
+            .columns()
+            .iter()
+            .map(|column| index_map.iter().map(|&index| column[index]).collect())
+            .collect();
+        *table = Table::new(columns, &in_names, &table.out_names());
+    }
+    for (_, table) in o_gal.global.iter_mut() {
+        let (in_names, index_map) = substitute(&table.in_names(), &resolved);
+        let column = index_map
+            .iter()
+            .map(|&index| table.columns()[0][index])
+            .collect();
+        *table = Table::new(vec![column], &in_names, &table.out_names());
+    }
+
+    o_gal.lut = lut;
+    Ok(())
+}
+
+/// Flattens the function of a node into one which only reads pins. `stack` holds the nodes which are
+/// being resolved, finding a node on it again means the nodes form a cycle.
+fn resolve(
+    name: &str,
+    nodes: &[String],
+    definitions: &HashMap<String, Node>,
+    resolved: &mut HashMap<String, Node>,
+    stack: &mut Vec<String>,
+) -> Result<(), String> {
+    if resolved.contains_key(name) {
+        return Ok(());
+    }
+    if let Some(start) = stack.iter().position(|node| node == name) {
+        let mut cycle = stack[start..].to_vec();
+        cycle.push(name.to_string());
+        return Err(format!("the nodes {} form a cycle", cycle.join(" -> ")));
+    }
+
+    let definition = match definitions.get(name) {
+        Some(definition) => definition,
+        None => return Err(format!("node {} has no function", name)),
+    };
+
+    stack.push(name.to_string());
+    for input in definition.in_names.iter() {
+        if nodes.contains(input) {
+            resolve(input, nodes, definitions, resolved, stack)?;
+        }
+    }
+    stack.pop();
+
+    let (in_names, index_map) = substitute(&definition.in_names, resolved);
+    let table = index_map
+        .iter()
+        .map(|&index| definition.table[index])
+        .collect();
+    resolved.insert(name.to_string(), Node { in_names, table });
+
+    Ok(())
+}
+
+/// Replaces the resolved nodes in a list of inputs with their inputs. Returns the new inputs and for
+/// every index of the new table the index of the old table which has the same value.
+fn substitute(in_names: &[String], resolved: &HashMap<String, Node>) -> (Vec<String>, Vec<usize>) {
+    let mut new_names: Vec<String> = Vec::new();
+    for name in in_names {
+        let names = match resolved.get(name) {
+            Some(node) => node.in_names.clone(),
+            None => vec![name.clone()],
+        };
+        for name in names {
+            if !new_names.contains(&name) {
+                new_names.push(name);
+            }
+        }
+    }
+
+    let bit = |names: &[String], name: &String, index: usize| {
+        let position = names.iter().position(|other| other == name).unwrap();
+        (index >> (names.len() - 1 - position)) & 1
+    };
+
+    let index_map = (0..1 << new_names.len())
+        .map(|k| {
+            in_names.iter().fold(0, |old, name| {
+                let value = match resolved.get(name) {
+                    Some(node) => {
+                        let index = node
+                            .in_names
+                            .iter()
+                            .fold(0, |index, input| index << 1 | bit(&new_names, input, k));
+                        node.table[index] as usize
+                    }
+                    None => bit(&new_names, name, k),
+                };
+                old << 1 | value
+            })
+        })
+        .collect();
+
+    (new_names, index_map)
+}
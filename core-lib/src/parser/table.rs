@@ -0,0 +1,209 @@
+use hardware_sim::LookupTable;
+
+/// Index of an input and the outputs a table row defines for it.
+type Row = (usize, Vec<Option<bool>>);
+
+/// A `LookupTable` whose entries can be don't care (`None`). The first input is the most significant
+/// bit of the table index.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Table {
+    in_names: Vec<String>,
+    out_names: Vec<String>,
+    columns: Vec<Vec<Option<bool>>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Vec<Option<bool>>>, in_names: &[String], out_names: &[String]) -> Self {
+        Self {
+            in_names: in_names.to_vec(),
+            out_names: out_names.to_vec(),
+            columns,
+        }
+    }
+
+    pub fn in_names(&self) -> Vec<String> {
+        self.in_names.clone()
+    }
+
+    pub fn out_names(&self) -> Vec<String> {
+        self.out_names.clone()
+    }
+
+    /// Returns the column of every output.
+    pub fn columns(&self) -> &[Vec<Option<bool>>] {
+        &self.columns
+    }
+
+    /// Returns the columns with every don't care entry as 0.
+    pub fn get_table(&self) -> Vec<Vec<bool>> {
+        self.columns
+            .iter()
+            .map(|column| column.iter().map(|value| value.unwrap_or(false)).collect())
+            .collect()
+    }
+
+    /// Returns the indices of the don't care entries of every output.
+    pub fn dont_care(&self) -> Vec<Vec<usize>> {
+        self.columns
+            .iter()
+            .map(|column| (0..column.len()).filter(|&i| column[i].is_none()).collect())
+            .collect()
+    }
+}
+
+impl From<LookupTable> for Table {
+    fn from(lut: LookupTable) -> Self {
+        let columns = lut
+            .get_table()
+            .into_iter()
+            .map(|column| column.into_iter().map(Some).collect())
+            .collect();
+        Self::new(columns, &lut.in_names(), &lut.out_names())
+    }
+}
+
+/// Builds the columns of a full table or, with a fill, of a fill table. Every row starts with the input
+/// pattern followed by the outputs.
+///
+/// The values and the don't cares are read as two boolean tables by the table parsers of
+/// `bool_algebra`. They don't know about `-` in the input pattern, so a row with a `-` is expanded to
+/// every row it matches first.
+pub fn parse_rows(
+    num_in: usize,
+    num_out: usize,
+    values: &[Option<bool>],
+    fill: Option<Option<bool>>,
+) -> Result<Vec<Vec<Option<bool>>>, String> {
+    let rows = expand_rows(num_in, num_out, values)?;
+    if fill.is_none() && rows.len() != 1 << num_in {
+        let mut defined = vec![false; 1 << num_in];
+        rows.iter().for_each(|&(index, _)| defined[index] = true);
+        let index = defined
+            .iter()
+            .position(|&defined| !defined)
+            .unwrap_or_default();
+        return Err(format!(
+            "table has no row for input {:0width$b}",
+            index,
+            width = num_in
+        ));
+    }
+
+    let flatten = |output: &dyn Fn(Option<bool>) -> bool| -> Vec<bool> {
+        let mut table = Vec::with_capacity(rows.len() * (num_in + num_out));
+        for (index, outputs) in rows.iter() {
+            table.extend((0..num_in).map(|i| (index >> (num_in - 1 - i)) & 1 == 1));
+            table.extend(outputs.iter().map(|&value| output(value)));
+        }
+        table
+    };
+    let values = flatten(&|value| value.unwrap_or(false));
+    let dont_care = flatten(&|value| value.is_none());
+
+    let (values, dont_care) = match fill {
+        Some(fill) => (
+            bool_algebra::parse_fill(num_in, num_out, values, fill.unwrap_or(false))?,
+            bool_algebra::parse_fill(num_in, num_out, dont_care, fill.is_none())?,
+        ),
+        None => (
+            bool_algebra::parse_full(num_in, num_out, values)?,
+            bool_algebra::parse_full(num_in, num_out, dont_care)?,
+        ),
+    };
+    Ok(merge(values, dont_care))
+}
+
+/// Builds the columns of a count table, each row only contains the outputs.
+pub fn parse_count(
+    num_in: usize,
+    num_out: usize,
+    values: &[Option<bool>],
+) -> Result<Vec<Vec<Option<bool>>>, String> {
+    let num_values = (1 << num_in) * num_out;
+    if values.len() != num_values {
+        return Err(format!(
+            "count table needs {} values but has {}",
+            num_values,
+            values.len()
+        ));
+    }
+
+    let split = |output: &dyn Fn(Option<bool>) -> bool| values.iter().map(|&v| output(v)).collect();
+    let vertical = crate::COUNT_VERTICAL;
+    Ok(merge(
+        bool_algebra::parse_count(num_in, num_out, split(&|v| v.unwrap_or(false)), vertical)?,
+        bool_algebra::parse_count(num_in, num_out, split(&|v| v.is_none()), vertical)?,
+    ))
+}
+
+/// Checks if the rows of a table define every input, a fill of such a table is never used.
+pub fn covers_every_input(num_in: usize, num_out: usize, values: &[Option<bool>]) -> bool {
+    expand_rows(num_in, num_out, values).is_ok_and(|rows| rows.len() == 1 << num_in)
+}
+
+/// Returns the index and the outputs of every input the rows define, in the order they are defined.
+/// A `-` in the input pattern matches both values, rows which define an input twice have to agree.
+fn expand_rows(num_in: usize, num_out: usize, values: &[Option<bool>]) -> Result<Vec<Row>, String> {
+    let row_len = num_in + num_out;
+    if !values.len().is_multiple_of(row_len) {
+        return Err(format!(
+            "table has {} values which is not a multiple of the row length {}",
+            values.len(),
+            row_len
+        ));
+    }
+
+    // outputs of every input which is already defined and the inputs in the order they are defined
+    let mut defined: Vec<Option<&[Option<bool>]>> = vec![None; 1 << num_in];
+    let mut order = Vec::new();
+    for (row_num, row) in values.chunks(row_len).enumerate() {
+        let (pattern, outputs) = row.split_at(num_in);
+
+        for (index, defined) in defined.iter_mut().enumerate() {
+            let matches = pattern.iter().enumerate().all(|(i, bit)| match bit {
+                Some(bit) => (index >> (num_in - 1 - i)) & 1 == *bit as usize,
+                None => true,
+            });
+            if !matches {
+                continue;
+            }
+
+            match defined {
+                Some(earlier) if *earlier != outputs => {
+                    return Err(format!(
+                        "row {} contradicts an earlier row for input {:0width$b}",
+                        row_num + 1,
+                        index,
+                        width = num_in
+                    ));
+                }
+                Some(_) => (),
+                None => {
+                    *defined = Some(outputs);
+                    order.push(index);
+                }
+            }
+        }
+    }
+
+    let rows = order
+        .into_iter()
+        .map(|index| (index, defined[index].unwrap_or_default().to_vec()))
+        .collect();
+    Ok(rows)
+}
+
+/// Combines the value and the don't care table of every output into one tri-state column.
+fn merge(values: Vec<Vec<bool>>, dont_care: Vec<Vec<bool>>) -> Vec<Vec<Option<bool>>> {
+    values
+        .into_iter()
+        .zip(dont_care)
+        .map(|(column, dont_care)| {
+            column
+                .into_iter()
+                .zip(dont_care)
+                .map(|(value, dont_care)| (!dont_care).then_some(value))
+                .collect()
+        })
+        .collect()
+}
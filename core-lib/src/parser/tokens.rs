@@ -0,0 +1,190 @@
+use super::Token;
+use crate::diagnostic::{self, Diagnostic, Span};
+use logos::Logos;
+use std::ops::Range;
+use tokenizer::{Tokenizer, TypeEq};
+
+/// Tokenizer which also knows where every token is in the source code, so its errors can point to
+/// the token.
+pub struct Tokens {
+    tokenizer: Tokenizer<Token>,
+    /// every token the tokenizer returns and its byte range
+    tokens: Vec<(Token, Range<usize>)>,
+    /// number of tokens which have been consumed
+    consumed: usize,
+    current: Option<Token>,
+    code: String,
+}
+
+impl Tokens {
+    pub fn new(code: &str) -> Self {
+        let ignore = Token::Ignore((0, None));
+        let tokens = Token::lexer(code)
+            .spanned()
+            .filter(|(token, _)| !token.type_eq(&ignore))
+            .collect();
+
+        Self {
+            tokenizer: Tokenizer::new(Token::lexer(code), vec![ignore]),
+            tokens,
+            consumed: 0,
+            current: None,
+            code: code.to_string(),
+        }
+    }
+
+    pub fn peek(&mut self) -> Option<Token> {
+        self.tokenizer.peek()
+    }
+
+    /// Returns the token after the next one, some names are only keywords if a certain token follows.
+    pub fn peek_second(&self) -> Option<Token> {
+        self.tokens
+            .get(self.consumed + 1)
+            .map(|(token, _)| token.clone())
+    }
+
+    pub fn next(&mut self) -> Option<Token> {
+        let token = self.tokenizer.next();
+        if token.is_some() {
+            self.consumed += 1;
+        }
+        self.current = token.clone();
+        token
+    }
+
+    pub fn next_is(&mut self, token: Token) -> bool {
+        self.tokenizer.next_is(token)
+    }
+
+    pub fn expect_next(&mut self, token: Token) -> Result<Token, Diagnostic> {
+        match self.next() {
+            Some(next) if next.type_eq(&token) => Ok(next),
+            _ => Err(self.unexpected(&[token])),
+        }
+    }
+
+    /// Checks the last consumed token.
+    pub fn expect(&mut self, token: Token) -> Result<(), Diagnostic> {
+        self.expect_multi(vec![token])
+    }
+
+    /// Checks the last consumed token.
+    pub fn expect_multi(&mut self, tokens: Vec<Token>) -> Result<(), Diagnostic> {
+        match &self.current {
+            Some(current) if tokens.iter().any(|token| current.type_eq(token)) => Ok(()),
+            _ => Err(self.unexpected(&tokens)),
+        }
+    }
+
+    /// Skips the rest of a statement after an error. A statement ends with a `;` or, if it is a
+    /// table, with a `}`, so the parser can go on after it.
+    pub fn recover(&mut self) {
+        while !matches!(
+            self.current,
+            Some(Token::Semicolon) | Some(Token::CurlyClose)
+        ) {
+            if self.next().is_none() {
+                return;
+            }
+        }
+    }
+
+    /// Returns an error which points to the last consumed token.
+    pub fn error(&mut self, code: &str, msg: &str) -> Diagnostic {
+        Diagnostic::error(code, msg, Some(self.span()))
+    }
+
+    /// Returns the byte range of the next token, or the end of the code if there is none.
+    pub fn next_span(&self) -> Range<usize> {
+        self.range(self.consumed)
+    }
+
+    /// Returns the position of the last consumed token.
+    pub fn span(&self) -> Span {
+        let range = self.range(self.consumed.saturating_sub(1));
+        Span::new(&self.code, range)
+    }
+
+    fn range(&self, index: usize) -> Range<usize> {
+        match self.tokens.get(index) {
+            Some((_, span)) => span.clone(),
+            None => self.code.len()..self.code.len(),
+        }
+    }
+
+    /// Returns an error for the last consumed token which isn't one of the expected tokens or names.
+    /// The names are keywords which are only reserved in some places, like the `oe` of `x.oe`.
+    pub fn unexpected_keyword(&self, expected: &[Token], keywords: &[&str]) -> Diagnostic {
+        let mut names: Vec<String> = expected.iter().map(describe).collect();
+        names.extend(keywords.iter().map(|keyword| format!("`{}`", keyword)));
+        self.expected(names)
+    }
+
+    fn unexpected(&self, expected: &[Token]) -> Diagnostic {
+        self.expected(expected.iter().map(describe).collect())
+    }
+
+    fn expected(&self, mut names: Vec<String>) -> Diagnostic {
+        let last = names.pop().unwrap_or_default();
+        let expected = if names.is_empty() {
+            last
+        } else {
+            format!("{} or {}", names.join(", "), last)
+        };
+        let found = match &self.current {
+            Some(token) => describe(token),
+            None => "the end of the code".to_string(),
+        };
+
+        Diagnostic::error(
+            diagnostic::SYNTAX,
+            &format!("expected {}, found {}", expected, found),
+            Some(self.span()),
+        )
+    }
+}
+
+/// Returns the first position of a name in the code. Names which are created by a range, like the
+/// `i0` of `i[0..1]`, are not found.
+pub fn find_name(code: &str, name: &str) -> Option<Span> {
+    Token::lexer(code)
+        .spanned()
+        .find(|(token, _)| *token == Token::Identifier(name.to_string()))
+        .map(|(_, range)| Span::new(code, range))
+}
+
+/// Describes a token for an error message, e.g. "`;`" or "a name".
+fn describe(token: &Token) -> String {
+    let text = match token {
+        Token::Pin => "pin",
+        Token::Table => "table",
+        Token::Count => "count",
+        Token::Fill => "fill",
+        Token::Dff => "dff",
+        Token::Comma => ",",
+        Token::Semicolon => ";",
+        Token::Equals => "=",
+        Token::Dot => ".",
+        Token::And => "&",
+        Token::Or => "|",
+        Token::Xor => "^",
+        Token::Not => "!",
+        Token::CurlyOpen => "{",
+        Token::RoundOpen => "(",
+        Token::SquareOpen => "[",
+        Token::CurlyClose => "}",
+        Token::RoundClose => ")",
+        Token::SquareClose => "]",
+        Token::Arrow => "->",
+        Token::Minus => "-",
+        Token::Identifier(name) if name.is_empty() => return "a name".to_string(),
+        Token::Identifier(name) => name,
+        Token::Number(num) if num.is_empty() => return "a number".to_string(),
+        Token::Number(num) => num,
+        Token::Ignore(_) => return "a comment".to_string(),
+        Token::Unknown => return "an unknown character".to_string(),
+    };
+
+    format!("`{}`", text)
+}
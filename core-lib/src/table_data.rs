@@ -1,5 +1,6 @@
 use crate::CircuitConfig;
 use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
 
 /// This data structure contains following data from processed expressions.
 ///
@@ -7,12 +8,69 @@ use serde::{Deserialize, Serialize};
 /// - "output_pin" stores the output pin
 /// - "table" contains the truth table for the expression and is used to generate a dnf expression later on
 /// - "enable_flip_flop" holds a boolean which decides if the output pin should have its flip flop turned on.
-#[derive(PartialEq, Debug, Clone, Hash, Serialize, Deserialize)]
+/// - "dont_care" lists the indices of the truth table whose value doesn't matter, the minimizer is free to pick
+///   either value for them
+/// - "active_low" selects the inverting output polarity. The table always holds the level of the pin, so an
+///   active low output gets the complement of the table as product terms
+/// - "oe" is the truth table of the output enable, without one the output is always enabled
+/// - "global" marks the table of a global product term like the asynchronous reset, such a table
+///   doesn't drive an output and its "output_pin" is ignored
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct TableData {
     pub input_pins: Vec<u32>,
+    #[serde(default)]
     pub output_pin: u32,
     pub table: Vec<bool>,
     pub enable_flip_flop: bool,
+    #[serde(default)]
+    pub dont_care: Vec<usize>,
+    #[serde(default)]
+    pub active_low: bool,
+    #[serde(default)]
+    pub oe: Option<OutputEnable>,
+    #[serde(default)]
+    pub global: Option<GlobalTerm>,
+}
+
+/// Product terms which act on all registered outputs of a 22V10.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum GlobalTerm {
+    /// asynchronous reset, clears all flip flops while it is true
+    #[serde(rename = "ar")]
+    AsynchronousReset,
+    /// synchronous preset, sets all flip flops on the next clock while it is true
+    #[serde(rename = "sp")]
+    SynchronousPreset,
+}
+
+/// Truth table of the output enable of an output, it has to fit in a single product term.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Serialize, Deserialize)]
+pub struct OutputEnable {
+    pub input_pins: Vec<u32>,
+    pub table: Vec<bool>,
+}
+
+// tables of active high outputs without don't cares and output enable hash like they did before the
+// fields existed, the WinCUPL transpiler derives its field names from the hash
+impl Hash for TableData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.input_pins.hash(state);
+        self.output_pin.hash(state);
+        self.table.hash(state);
+        self.enable_flip_flop.hash(state);
+        if !self.dont_care.is_empty() {
+            self.dont_care.hash(state);
+        }
+        if self.active_low {
+            self.active_low.hash(state);
+        }
+        if let Some(oe) = &self.oe {
+            oe.hash(state);
+        }
+        if let Some(global) = &self.global {
+            global.hash(state);
+        }
+    }
 }
 
 impl TableData {
@@ -27,6 +85,10 @@ impl TableData {
             output_pin,
             table,
             enable_flip_flop,
+            dont_care: Vec::new(),
+            active_low: false,
+            oe: None,
+            global: None,
         }
     }
 
@@ -36,11 +98,28 @@ impl TableData {
         } else if self.table.len() != 2_usize.pow(self.input_pins.len() as u32) {
             return Err("Truth table size doesn't match input bits".to_string());
         }
+        if self.dont_care.iter().any(|&i| i >= self.table.len()) {
+            return Err("Don't care index is outside of the truth table".to_string());
+        }
+        // special pins like pin 13 of the 22V10 are dedicated inputs with a column of their own
+        let is_input = |pin: &u32| {
+            config.inputs.contains(pin) || config.special_pins.iter().any(|(other, _)| other == pin)
+        };
         for pin in self.input_pins.clone() {
-            if !config.inputs.contains(&pin) {
+            if !is_input(&pin) {
                 return Err(format!("input pin {} is not defined in config", pin));
             }
         }
+        if let Some(oe) = &self.oe {
+            if oe.table.len() != 2_usize.pow(oe.input_pins.len() as u32) {
+                return Err("Output enable table size doesn't match input bits".to_string());
+            }
+            for pin in oe.input_pins.iter() {
+                if !is_input(pin) {
+                    return Err(format!("input pin {} is not defined in config", pin));
+                }
+            }
+        }
 
         let output1: Vec<u32> = config
             .outputs
@@ -55,7 +134,10 @@ impl TableData {
             .map(|(_, e)| e.clone())
             .collect();
 
-        if !output1.contains(&self.output_pin) && !output2.contains(&self.output_pin) {
+        if self.global.is_none()
+            && !output1.contains(&self.output_pin)
+            && !output2.contains(&self.output_pin)
+        {
             return Err(format!(
                 "output pin {} is not defined in config",
                 self.output_pin
@@ -1,19 +1,163 @@
+use crate::circuit_config::Layout;
+use crate::diagnostic::{self, Diagnostic};
 use crate::translator::*;
 use crate::{CircuitConfig, TableData};
 
-use super::dnf::Expression;
+use super::dnf::{Expression, GlobalTerms};
+use super::fitter::{self, Report};
+use super::jedec::FuseList;
+use super::minimize::Effort;
+
+/// Number of bytes of the user electronic signature.
+const UES_BYTES: usize = 8;
+
+/// Options which control how the truth tables are translated into a fuse map.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Options {
+    /// algorithm which is used to minimize the truth tables
+    pub effort: Effort,
+    /// user electronic signature, at most 8 bytes which are written to the UES fuses
+    pub ues: Vec<u8>,
+    /// enables the pin controlled power-down of parts with a power-down fuse (Atmel ATF22V10C and ATF16V8B)
+    pub power_down: bool,
+    /// lets combinatorial outputs use the inverted polarity if its complement needs fewer product terms
+    pub auto_polarity: bool,
+    /// notes which are written to the JEDEC file, e.g. the pinout the fitter chose
+    pub notes: Vec<String>,
+    /// moves the product terms which don't fit an OLMC to an unused OLMC and feeds them back (22V10 only)
+    pub split_terms: bool,
+    /// how the fuses are written to the JEDEC file
+    pub fuse_list: FuseList,
+}
 
 pub fn to_jedec(
     truth_tables: &Vec<TableData>,
     config: &CircuitConfig,
     head: Option<String>,
-) -> Result<String, String> {
+    options: &Options,
+) -> Result<String, Diagnostic> {
+    compile(truth_tables, config, head, options).map(|(jedec, _)| jedec)
+}
+
+/// Like `to_jedec` but also returns the report of the fitter. Errors of the later stages have no
+/// span, they are about the device and not about a position in the code.
+pub fn compile(
+    truth_tables: &[TableData],
+    config: &CircuitConfig,
+    head: Option<String>,
+    options: &Options,
+) -> Result<(String, Report), Diagnostic> {
+    let (global, outputs): (Vec<&TableData>, Vec<&TableData>) = truth_tables
+        .iter()
+        .partition(|truth_table| truth_table.global.is_some());
+
+    for (i, truth_table) in outputs.iter().enumerate() {
+        if outputs[..i]
+            .iter()
+            .any(|other| other.output_pin == truth_table.output_pin)
+        {
+            return Err(Diagnostic::error(
+                diagnostic::MULTIPLE_DRIVERS,
+                &format!(
+                    "pin {} is driven by more than one table",
+                    truth_table.output_pin
+                ),
+                None,
+            ));
+        }
+    }
+
     let mut exprs = Vec::new();
-    for truth_table in truth_tables {
-        exprs.push(Expression::new(truth_table, config)?);
+    for truth_table in outputs {
+        exprs.push(Expression::new(truth_table, config, options)?);
+    }
+    let global = GlobalTerms::new(&global, config, options)?;
+
+    let mut report = Report::default();
+    if options.split_terms {
+        report.splits = fitter::split_terms(&mut exprs, &global, config)
+            .map_err(|msg| Diagnostic::error(diagnostic::FIT, &msg, None))?;
+    }
+
+    let mut fuses = match &config.layout {
+        Layout::Gal22V10(_) => fuses::build(&exprs, &global, config)?,
+        Layout::V8(_) if !global.is_empty() => {
+            return Err("ar and sp are only available on 22V10 devices"
+                .to_string()
+                .into());
+        }
+        Layout::V8(layout) => v8::build(&exprs, layout, config.num_fuses)?,
+    };
+
+    match config.ues_fuse {
+        Some(index) => set_ues(&mut fuses, index as usize, &options.ues)?,
+        None if !options.ues.is_empty() => {
+            return Err("the device has no user electronic signature"
+                .to_string()
+                .into());
+        }
+        None => (),
+    }
+
+    match config.power_down_fuse {
+        Some(index) if (index as usize) < fuses.len() => fuses[index as usize] = options.power_down,
+        Some(index) => {
+            return Err(format!("power-down fuse {} is outside of the fuse map", index).into())
+        }
+        None if options.power_down => {
+            return Err("the device has no power-down fuse".to_string().into());
+        }
+        None => (),
+    }
+
+    if options.fuse_list.width == 0 {
+        return Err("the width of a fuse list block must be at least 1"
+            .to_string()
+            .into());
+    }
+    let jedec = jedec::jedec(
+        config.num_pins,
+        config.num_fuses,
+        fuses,
+        head,
+        &options.notes,
+        &options.fuse_list,
+    );
+    Ok((jedec, report))
+}
+
+/// Writes the signature to the 64 UES fuses, every byte starts with its most significant bit.
+fn set_ues(fuses: &mut [bool], ues_start: usize, ues: &[u8]) -> Result<(), String> {
+    if ues.len() > UES_BYTES {
+        return Err(format!(
+            "the user electronic signature has {} bytes but only {} fit",
+            ues.len(),
+            UES_BYTES
+        ));
+    } else if ues_start + UES_BYTES * 8 > fuses.len() {
+        return Err("UES fuses are outside of the fuse map".to_string());
+    }
+
+    for (i, byte) in ues.iter().enumerate() {
+        for bit in 0..8 {
+            fuses[ues_start + i * 8 + bit] = byte & (0x80 >> bit) != 0;
+        }
     }
 
-    let fuses = fuses::build(&exprs, config)?;
+    Ok(())
+}
 
-    Ok(jedec::jedec(config.num_pins, config.num_fuses, fuses, head))
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn set_ues() {
+        let mut fuses = vec![false; 70];
+        super::set_ues(&mut fuses, 4, &[0x81, 0x40]).unwrap();
+
+        assert!(fuses[4] && fuses[11] && fuses[13]);
+        assert_eq!(fuses.iter().filter(|&&b| b).count(), 3);
+
+        assert!(super::set_ues(&mut fuses, 4, &[0; 9]).is_err());
+        assert!(super::set_ues(&mut fuses, 8, &[0]).is_err());
+    }
 }
@@ -0,0 +1,292 @@
+use super::jedec;
+use super::v8::{Mode, ROWS_PER_OLMC};
+use crate::circuit_config::{Gal22V10Layout, Layout, V8Layout};
+use crate::diagnostic::Diagnostic;
+use crate::CircuitConfig;
+
+/// Product term of a row, the pins which are connected and if their inverted column is used. An
+/// empty term is always true.
+type Term = Vec<(u32, bool)>;
+
+/// An OLMC which drives its pin.
+#[derive(PartialEq, Debug, Clone)]
+struct Output {
+    pin: u32,
+    registered: bool,
+    active_low: bool,
+    /// the OE term, `None` if the output is always enabled or enabled by the OE pin
+    oe: Option<Term>,
+    terms: Vec<Term>,
+}
+
+/// Reads the fuse map of a JEDEC file and writes it as OpenGAL code. Every OLMC which can drive its
+/// pin becomes an equation with its flip flop, polarity and OE term, pins are named after their
+/// number (`i2` for an input, `o19` for an output).
+pub fn decompile(jedec: &str, config: &CircuitConfig) -> Result<String, Diagnostic> {
+    let file = jedec::parse(jedec)?;
+    if file.fuses.len() != config.num_fuses as usize {
+        return Err(format!(
+            "the JEDEC file has {} fuses but the device has {}",
+            file.fuses.len(),
+            config.num_fuses
+        )
+        .into());
+    }
+
+    let (outputs, global, comment) = match &config.layout {
+        Layout::Gal22V10(layout) => (
+            gal22v10(&file.fuses, layout, config)?,
+            global(&file.fuses, layout),
+            None,
+        ),
+        Layout::V8(layout) => {
+            let (outputs, mode) = v8(&file.fuses, layout)?;
+            let comment = match mode {
+                Mode::Simple => "the device is in simple mode".to_string(),
+                Mode::Complex => "the device is in complex mode".to_string(),
+                Mode::Registered => format!(
+                    "the device is in registered mode, pin {} is the clock and pin {} enables the registered outputs",
+                    layout.clock_pin, layout.oe_pin
+                ),
+            };
+            (outputs, Vec::new(), Some(comment))
+        }
+    };
+
+    Ok(source(&outputs, &global, comment))
+}
+
+/// Reads the OLMCs of a 22V10. An OLMC whose OE term is never true doesn't drive its pin, the pin is
+/// an input.
+fn gal22v10(
+    fuses: &[bool],
+    layout: &Gal22V10Layout,
+    config: &CircuitConfig,
+) -> Result<Vec<Output>, String> {
+    let num_columns = layout.num_columns as usize;
+    let row = |start: usize| &fuses[start..start + num_columns];
+
+    let mut modes = Vec::new();
+    for &(pin, _) in config.outputs.iter() {
+        let olmc = match layout.olmcs.iter().find(|olmc| olmc.pin == pin) {
+            Some(olmc) => olmc,
+            None => return Err(format!("output pin {} has no OLMC", pin)),
+        };
+        if never_true(row(olmc.first_fuse as usize), &layout.columns) {
+            continue;
+        }
+        // S0 is set for active high outputs, S1 for combinatorial ones
+        modes.push((olmc, !fuses[olmc.s1 as usize], !fuses[olmc.s0 as usize]));
+    }
+
+    // a registered active high output feeds back /Q, which is the complement of its pin
+    let inverted_feedback: Vec<u32> = modes
+        .iter()
+        .filter(|(_, registered, active_low)| *registered && !active_low)
+        .map(|(olmc, _, _)| olmc.pin)
+        .collect();
+
+    let mut outputs = Vec::new();
+    for (olmc, registered, active_low) in modes {
+        let terms = config
+            .outputs
+            .iter()
+            .find(|(pin, _)| *pin == olmc.pin)
+            .map_or(0, |&(_, terms)| terms as usize);
+        let first = olmc.first_fuse as usize;
+
+        let oe = term(row(first), &layout.columns, &inverted_feedback);
+        outputs.push(Output {
+            pin: olmc.pin,
+            registered,
+            active_low,
+            oe: oe.filter(|oe| !oe.is_empty()),
+            terms: (1..=terms)
+                .filter_map(|i| {
+                    term(
+                        row(first + i * num_columns),
+                        &layout.columns,
+                        &inverted_feedback,
+                    )
+                })
+                .collect(),
+        });
+    }
+
+    Ok(outputs)
+}
+
+/// Reads the AR and SP rows of a 22V10, a row which is never true is unused.
+fn global(fuses: &[bool], layout: &Gal22V10Layout) -> Vec<(&'static str, Term)> {
+    let num_columns = layout.num_columns as usize;
+    [("ar", layout.ar), ("sp", layout.sp)]
+        .into_iter()
+        .filter_map(|(name, start)| {
+            let row = &fuses[start as usize..start as usize + num_columns];
+            term(row, &layout.columns, &[]).map(|term| (name, term))
+        })
+        .collect()
+}
+
+/// Reads the OLMCs of a GAL16V8 or GAL20V8 and the mode of the device.
+fn v8(fuses: &[bool], layout: &V8Layout) -> Result<(Vec<Output>, Mode), String> {
+    let mode = match (fuses[layout.syn as usize], fuses[layout.ac0 as usize]) {
+        (true, false) => Mode::Simple,
+        (true, true) => Mode::Complex,
+        (false, true) => Mode::Registered,
+        (false, false) => return Err("the SYN and AC0 fuses select no mode".to_string()),
+    };
+    let num_columns = layout.num_columns as usize;
+    let columns = layout.columns(mode);
+    let row = |index: usize| &fuses[index * num_columns..(index + 1) * num_columns];
+    // the product term disable fuse of a row is cleared if the row is unused
+    let enabled = |index: usize| fuses[layout.ptd as usize + index];
+
+    // (pin, first row, registered, active low, the first row is the OE term)
+    let mut modes = Vec::new();
+    for (olmc, &pin) in layout.olmc_pins.iter().enumerate() {
+        let ac1 = fuses[layout.ac1 as usize + olmc];
+        let active_low = !fuses[layout.xor as usize + olmc];
+        let first_row = olmc * ROWS_PER_OLMC;
+
+        match (mode, ac1) {
+            (Mode::Simple, true) => (),
+            (Mode::Simple, false) => modes.push((pin, first_row, false, active_low, false)),
+            (Mode::Registered, false) => modes.push((pin, first_row, true, active_low, false)),
+            (Mode::Complex, _) | (Mode::Registered, true) => {
+                if !never_true(row(first_row), columns) && enabled(first_row) {
+                    modes.push((pin, first_row, false, active_low, true));
+                }
+            }
+        }
+    }
+
+    // the XOR is in front of the flip flop, so unlike on the 22V10 every feedback is the level of
+    // its pin
+    let outputs = modes
+        .into_iter()
+        .map(|(pin, first_row, registered, active_low, has_oe)| {
+            let first_term = first_row + has_oe as usize;
+            let oe = match has_oe {
+                true => term(row(first_row), columns, &[]),
+                false => None,
+            };
+            Output {
+                pin,
+                registered,
+                active_low,
+                oe: oe.filter(|oe| !oe.is_empty()),
+                terms: (first_term..first_row + ROWS_PER_OLMC)
+                    .filter(|&index| enabled(index))
+                    .filter_map(|index| term(row(index), columns, &[]))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok((outputs, mode))
+}
+
+/// Checks if a row connects a signal and its complement, an unused row has every fuse intact.
+fn never_true(row: &[bool], columns: &[(u32, u32)]) -> bool {
+    columns.iter().any(|&(_, column)| {
+        let column = column as usize;
+        !row[column] && !row[column + 1]
+    })
+}
+
+/// Reads the product term of a row, the fuse of a connected column is intact. Returns `None` if the
+/// term is never true.
+fn term(row: &[bool], columns: &[(u32, u32)], inverted_feedback: &[u32]) -> Option<Term> {
+    if never_true(row, columns) {
+        return None;
+    }
+
+    let mut term = Vec::new();
+    for &(pin, column) in columns.iter() {
+        for inverted in [false, true] {
+            if !row[column as usize + inverted as usize] {
+                term.push((pin, inverted != inverted_feedback.contains(&pin)));
+            }
+        }
+    }
+    term.sort();
+    Some(term)
+}
+
+/// Writes the OLMCs as code. The terms are pin levels, an active low output reads its own pin
+/// inverted.
+fn source(outputs: &[Output], global: &[(&str, Term)], comment: Option<String>) -> String {
+    let output = |pin: u32| outputs.iter().find(|output| output.pin == pin);
+    let name = |pin: u32| match output(pin) {
+        Some(_) => format!("o{}", pin),
+        None => format!("i{}", pin),
+    };
+    let literal = |&(pin, inverted): &(u32, bool)| {
+        let active_low = output(pin).is_some_and(|output| output.active_low);
+        let not = if inverted != active_low { "!" } else { "" };
+        format!("{}{}", not, name(pin))
+    };
+    let sum = |terms: &[Term]| {
+        if terms.iter().any(|term| term.is_empty()) {
+            return "1".to_string();
+        } else if terms.is_empty() {
+            return "0".to_string();
+        }
+        terms
+            .iter()
+            .map(|term| {
+                term.iter()
+                    .map(literal)
+                    .collect::<Vec<String>>()
+                    .join(" & ")
+            })
+            .collect::<Vec<String>>()
+            .join(" | ")
+    };
+
+    let mut pins: Vec<u32> = outputs.iter().map(|output| output.pin).collect();
+    for output in outputs.iter() {
+        for term in output.terms.iter().chain(output.oe.iter()) {
+            pins.extend(term.iter().map(|&(pin, _)| pin));
+        }
+    }
+    for (_, term) in global.iter() {
+        pins.extend(term.iter().map(|&(pin, _)| pin));
+    }
+    pins.sort();
+    pins.dedup();
+
+    let mut code = String::new();
+    if let Some(comment) = comment {
+        code += &format!("// {}\n", comment);
+    }
+    for &pin in pins.iter() {
+        let not = if output(pin).is_some_and(|output| output.active_low) {
+            "!"
+        } else {
+            ""
+        };
+        code += &format!("pin {} = {}{};\n", pin, not, name(pin));
+    }
+
+    for output in outputs.iter() {
+        let name = name(output.pin);
+        code += &format!("\n{} = {};\n", name, sum(&output.terms));
+        if output.registered {
+            code += &format!("{}.dff;\n", name);
+        }
+        if let Some(oe) = &output.oe {
+            code += &format!("{}.oe = {};\n", name, sum(std::slice::from_ref(oe)));
+        }
+    }
+
+    if !global.is_empty() {
+        code.push('\n');
+    }
+    for (name, term) in global.iter() {
+        code += &format!("{} = {};\n", name, sum(std::slice::from_ref(term)));
+    }
+
+    code
+}
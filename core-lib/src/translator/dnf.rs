@@ -1,4 +1,5 @@
-use crate::{CircuitConfig, TableData};
+use crate::translator::minimize::{minimize, Cube};
+use crate::{CircuitConfig, GlobalTerm, Options, TableData};
 
 #[derive(PartialEq, Debug, Clone)]
 
@@ -16,23 +17,44 @@ impl Pin {
 #[derive(Debug, Clone)]
 pub struct Row {
     pub pins: Vec<Pin>,
+    /// the row keeps every fuse intact, which connects each signal and its complement, so it is never
+    /// true like an unused row
+    pub never_true: bool,
 }
 
 impl Row {
-    pub fn new(mut bits: Vec<bool>, inputs: Vec<u32>) -> Self {
+    /// builds a row from a minimized term, the first input pin is the most significant bit
+    /// of the truth table index
+    pub fn new(cube: Cube, inputs: &[u32]) -> Self {
         let mut pins = Vec::new();
-        bits.reverse();
 
-        for i in 0..inputs.len() {
-            pins.push(Pin::new(!bits[i], inputs[i]));
+        for (i, &pin) in inputs.iter().enumerate() {
+            let bit = 1 << (inputs.len() - 1 - i);
+            if cube.mask & bit != 0 {
+                pins.push(Pin::new(cube.value & bit == 0, pin));
+            }
         }
 
-        Self { pins }
+        Self {
+            pins,
+            never_true: false,
+        }
+    }
+
+    /// builds a row which is never true
+    pub fn never_true() -> Self {
+        Self {
+            pins: Vec::new(),
+            never_true: true,
+        }
     }
 }
 
 impl PartialEq for Row {
     fn eq(&self, other: &Row) -> bool {
+        if self.never_true != other.never_true {
+            return false;
+        }
         for pin in self.pins.clone() {
             if !other.pins.contains(&pin) {
                 return false;
@@ -46,86 +68,138 @@ impl PartialEq for Row {
 pub struct Expression {
     pub out_pin: u32,
     pub enable_flip_flop: bool,
+    /// the OR of the rows is inverted before it drives the pin
+    pub active_low: bool,
+    /// product term of the output enable, `None` if the output is always enabled
+    pub oe: Option<Row>,
     pub rows: Vec<Row>,
 }
 
 impl Expression {
-    pub fn new(truth_table: &TableData, config: &CircuitConfig) -> Result<Self, String> {
+    pub fn new(
+        truth_table: &TableData,
+        config: &CircuitConfig,
+        options: &Options,
+    ) -> Result<Self, String> {
         truth_table.valid(config)?;
 
-        let mut rows = Vec::new();
+        let num_inputs = truth_table.input_pins.len();
+        let terms =
+            |table: &[bool]| minimize(num_inputs, table, &truth_table.dont_care, options.effort);
+
+        // an active low output gets the terms of the complement
+        let complement: Vec<bool> = truth_table.table.iter().map(|&val| !val).collect();
+        let (declared, other) = if truth_table.active_low {
+            (&complement, &truth_table.table)
+        } else {
+            (&truth_table.table, &complement)
+        };
 
-        for (i, &val) in truth_table.table.clone().iter().enumerate() {
-            if val {
-                let bits = uint_to_bool_vec(i as u32);
-                rows.push(Row::new(bits, truth_table.input_pins.clone()));
+        // with auto polarity combinatorial outputs use the polarity which needs fewer terms. Registered
+        // outputs keep the declared one because it decides the level of the pin after a reset.
+        let declared = terms(declared);
+        let (active_low, cover) = if !options.auto_polarity || truth_table.enable_flip_flop {
+            (truth_table.active_low, declared)
+        } else {
+            let other = terms(other);
+            if other.len() < declared.len() {
+                (!truth_table.active_low, other)
+            } else {
+                (truth_table.active_low, declared)
             }
-        }
+        };
+
+        let rows = cover
+            .into_iter()
+            .map(|cube| Row::new(cube, &truth_table.input_pins))
+            .collect();
+
+        let oe = match &truth_table.oe {
+            Some(oe) => Some(single_term(
+                &oe.input_pins,
+                &oe.table,
+                options,
+                &format!("the output enable of pin {}", truth_table.output_pin),
+            )?),
+            None => None,
+        };
+
         Ok(Self {
             out_pin: truth_table.output_pin,
             enable_flip_flop: truth_table.enable_flip_flop,
+            active_low,
+            oe,
             rows,
         })
     }
 }
 
-fn uint_to_bool_vec(num: u32) -> Vec<bool> {
-    let mut result = Vec::with_capacity(32);
-    let string_rep = format!("{:#034b}", num);
-
-    // skip 0b
-    for c in string_rep.chars().skip(2) {
-        let val = match c {
-            '0' => false,
-            '1' => true,
-            _ => panic!("unexpected char {} expected 1 or 0", c), // only has 0  or 1
-        };
-        result.push(val);
-    }
-
-    result
+/// Product terms of the global rows of a 22V10, `None` if the design doesn't use the term.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct GlobalTerms {
+    /// asynchronous reset
+    pub ar: Option<Row>,
+    /// synchronous preset
+    pub sp: Option<Row>,
 }
 
-#[cfg(test)]
-mod tests {
+impl GlobalTerms {
+    /// Minimizes the tables of the global terms, every term has to fit into its single row.
+    pub fn new(
+        truth_tables: &[&TableData],
+        config: &CircuitConfig,
+        options: &Options,
+    ) -> Result<Self, String> {
+        let mut terms = Self::default();
 
-    #[test]
-    fn bit_convesion() {
-        let bits = super::uint_to_bool_vec(5); // 5 -> 0101
-        assert_eq!(bits.len(), 32);
-        assert_eq!(
-            bits,
-            vec![
-                false, false, false, false, false, false, false, false, //
-                false, false, false, false, false, false, false, false, //
-                false, false, false, false, false, false, false, false, //
-                false, false, false, false, false, true, false, true
-            ]
-        );
+        for truth_table in truth_tables {
+            truth_table.valid(config)?;
 
-        assert_eq!(
-            super::uint_to_bool_vec(238934),
-            vec![
-                false, false, false, false, false, false, false, false, //
-                false, false, false, false, false, false, true, true, //
-                true, false, true, false, false, true, false, true, //
-                false, true, false, true, false, true, true, false //
-            ]
-        );
+            let (term, name) = match truth_table.global {
+                Some(GlobalTerm::AsynchronousReset) => (&mut terms.ar, "the asynchronous reset"),
+                Some(GlobalTerm::SynchronousPreset) => (&mut terms.sp, "the synchronous preset"),
+                None => continue,
+            };
+            if term.is_some() {
+                return Err(format!("{} is defined more than once", name));
+            }
+            *term = Some(single_term(
+                &truth_table.input_pins,
+                &truth_table.table,
+                options,
+                name,
+            )?);
+        }
 
-        assert_eq!(
-            super::uint_to_bool_vec(0xff),
-            vec![
-                false, false, false, false, false, false, false, false, //
-                false, false, false, false, false, false, false, false, //
-                false, false, false, false, false, false, false, false, //
-                true, true, true, true, true, true, true, true //
-            ]
-        );
+        Ok(terms)
+    }
 
-        assert_eq!(super::uint_to_bool_vec(std::u32::MIN), vec![false; 32]);
-        assert_eq!(super::uint_to_bool_vec(std::u32::MAX), vec![true; 32]);
+    pub fn is_empty(&self) -> bool {
+        self.ar.is_none() && self.sp.is_none()
     }
+}
+
+/// Minimizes a table which has to fit into a single product term, e.g. an output enable. A table
+/// which is never true gets a row with every fuse intact.
+fn single_term(
+    input_pins: &[u32],
+    table: &[bool],
+    options: &Options,
+    name: &str,
+) -> Result<Row, String> {
+    let cover = minimize(input_pins.len(), table, &[], options.effort);
+    match cover.len() {
+        1 => Ok(Row::new(cover[0], input_pins)),
+        0 => Ok(Row::never_true()),
+        terms => Err(format!(
+            "{} needs {} product terms but only one is available",
+            name, terms
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
 
     #[test]
     fn expression_new() {
@@ -168,56 +242,68 @@ mod tests {
             super::Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                active_low: false,
+                oe: None,
                 rows: vec![
                     super::Row {
-                        pins: vec![super::Pin::new(true, 3), super::Pin::new(true, 2)],
+                        pins: vec![super::Pin::new(true, 3)],
+                        never_true: false,
                     },
                     super::Row {
-                        pins: vec![super::Pin::new(false, 3), super::Pin::new(true, 2)],
-                    },
-                    super::Row {
-                        pins: vec![super::Pin::new(false, 3), super::Pin::new(false, 2)],
+                        pins: vec![super::Pin::new(false, 2)],
+                        never_true: false,
                     },
                 ],
             },
             super::Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                active_low: false,
+                oe: None,
                 rows: vec![super::Row {
-                    pins: vec![super::Pin::new(false, 11), super::Pin::new(true, 10)],
+                    pins: vec![super::Pin::new(false, 10), super::Pin::new(true, 11)],
+                    never_true: false,
                 }],
             },
             super::Expression {
                 out_pin: 17,
                 enable_flip_flop: false,
+                active_low: false,
+                oe: None,
                 rows: vec![super::Row {
-                    pins: vec![super::Pin::new(false, 11), super::Pin::new(false, 10)],
+                    pins: vec![super::Pin::new(false, 10), super::Pin::new(false, 11)],
+                    never_true: false,
                 }],
             },
             super::Expression {
                 out_pin: 19,
                 enable_flip_flop: false,
+                active_low: false,
+                oe: None,
                 rows: vec![
                     super::Row {
-                        pins: vec![super::Pin::new(true, 11), super::Pin::new(false, 10)],
+                        pins: vec![super::Pin::new(true, 10), super::Pin::new(false, 11)],
+                        never_true: false,
                     },
                     super::Row {
-                        pins: vec![super::Pin::new(false, 11), super::Pin::new(true, 10)],
+                        pins: vec![super::Pin::new(false, 10), super::Pin::new(true, 11)],
+                        never_true: false,
                     },
                 ],
             },
             super::Expression {
                 out_pin: 18,
                 enable_flip_flop: false,
+                active_low: false,
+                oe: None,
                 rows: vec![
                     super::Row {
-                        pins: vec![super::Pin::new(true, 11), super::Pin::new(false, 10)],
-                    },
-                    super::Row {
-                        pins: vec![super::Pin::new(false, 11), super::Pin::new(true, 10)],
+                        pins: vec![super::Pin::new(false, 11)],
+                        never_true: false,
                     },
                     super::Row {
-                        pins: vec![super::Pin::new(false, 11), super::Pin::new(false, 10)],
+                        pins: vec![super::Pin::new(false, 10)],
+                        never_true: false,
                     },
                 ],
             },
@@ -225,9 +311,104 @@ mod tests {
 
         for i in 0..table_data.len() {
             assert_eq!(
-                super::Expression::new(&table_data[i], &config),
+                super::Expression::new(&table_data[i], &config, &super::Options::default()),
                 Ok(expressions[i].clone())
             );
         }
     }
+    #[test]
+    fn polarity() {
+        let config = super::CircuitConfig::gal22v10();
+        let or = |enable_flip_flop, active_low| super::TableData {
+            active_low,
+            ..super::TableData::new(
+                vec![2, 3],
+                23,
+                vec![false, true, true, true],
+                enable_flip_flop,
+            )
+        };
+        let auto = super::Options {
+            auto_polarity: true,
+            ..Default::default()
+        };
+        let nor = vec![super::Row {
+            pins: vec![super::Pin::new(true, 2), super::Pin::new(true, 3)],
+            never_true: false,
+        }];
+
+        // an active low output gets the terms of the complement
+        let expr = super::Expression::new(&or(false, true), &config, &Default::default()).unwrap();
+        assert!(expr.active_low);
+        assert_eq!(expr.rows, nor);
+
+        let expr = super::Expression::new(&or(false, false), &config, &Default::default()).unwrap();
+        assert!(!expr.active_low);
+        assert_eq!(expr.rows.len(), 2);
+
+        let expr = super::Expression::new(&or(false, false), &config, &auto).unwrap();
+        assert!(expr.active_low);
+        assert_eq!(expr.rows, nor);
+
+        // registered outputs keep their polarity
+        let expr = super::Expression::new(&or(true, false), &config, &auto).unwrap();
+        assert!(!expr.active_low);
+        assert_eq!(expr.rows.len(), 2);
+    }
+    #[test]
+    fn output_enable() {
+        let config = super::CircuitConfig::gal22v10();
+        let mut table_data = super::TableData::new(vec![2], 23, vec![false, true], false);
+
+        table_data.oe = Some(crate::OutputEnable {
+            input_pins: vec![3, 4],
+            table: vec![false, false, true, false],
+        });
+        let expr = super::Expression::new(&table_data, &config, &Default::default()).unwrap();
+        assert_eq!(
+            expr.oe,
+            Some(super::Row {
+                pins: vec![super::Pin::new(false, 3), super::Pin::new(true, 4)],
+                never_true: false
+            })
+        );
+
+        // the OE row is a single product term
+        table_data.oe = Some(crate::OutputEnable {
+            input_pins: vec![3, 4],
+            table: vec![false, true, true, false],
+        });
+        assert!(super::Expression::new(&table_data, &config, &Default::default()).is_err());
+    }
+
+    #[test]
+    fn global_terms() {
+        let config = super::CircuitConfig::gal22v10();
+        let global = |term, table| super::TableData {
+            global: Some(term),
+            ..super::TableData::new(vec![2, 3], 0, table, false)
+        };
+        let reset = global(
+            super::GlobalTerm::AsynchronousReset,
+            vec![false, false, true, false],
+        );
+
+        let terms = super::GlobalTerms::new(&[&reset], &config, &Default::default()).unwrap();
+        assert_eq!(
+            terms.ar,
+            Some(super::Row {
+                pins: vec![super::Pin::new(false, 2), super::Pin::new(true, 3)],
+                never_true: false
+            })
+        );
+        assert_eq!(terms.sp, None);
+
+        // AR and SP are a single row each
+        let preset = global(
+            super::GlobalTerm::SynchronousPreset,
+            vec![false, true, true, false],
+        );
+        assert!(super::GlobalTerms::new(&[&preset], &config, &Default::default()).is_err());
+        assert!(super::GlobalTerms::new(&[&reset, &reset], &config, &Default::default()).is_err());
+    }
 }
@@ -0,0 +1,461 @@
+use crate::translator::minimize::{full_mask, Cube};
+
+/// Irredundant searches for the smallest subset of partially redundant cubes exactly if there are
+/// at most this many of them, otherwise redundant cubes are dropped greedily.
+const IRREDUNDANT_MAX_SEARCH: usize = 12;
+
+/// Last gasp merges every pair of cubes, so it is skipped for covers larger than this.
+const LAST_GASP_MAX_CUBES: usize = 64;
+
+/// Espresso style two level minimization. The on-set, don't-care-set and off-set are given as
+/// cube lists, so wide tables never have to be enumerated minterm by minterm. The result is an
+/// irredundant cover of prime implicants.
+///
+/// The loop follows the classic expand, irredundant and reduce iteration and stops as soon as
+/// an iteration doesn't lower the cost of the cover anymore.
+pub fn espresso(num_inputs: usize, on: Vec<Cube>, dc: &[Cube], off: &[Cube]) -> Vec<Cube> {
+    let vars = full_mask(num_inputs);
+
+    let mut cover = expand(on, off);
+    cover = irredundant(cover, dc, vars);
+
+    loop {
+        loop {
+            let mut next = reduce(cover.clone(), dc);
+            next = expand(next, off);
+            next = irredundant(next, dc, vars);
+
+            if cost(&next) >= cost(&cover) {
+                break;
+            }
+            cover = next;
+        }
+
+        let next = last_gasp(&cover, dc, off, vars);
+        if cost(&next) >= cost(&cover) {
+            break;
+        }
+        cover = next;
+    }
+
+    cover
+}
+
+/// Splits a truth table into aligned blocks whose entries all equal `value` and returns them as cubes.
+pub fn table_cubes<T: PartialEq>(num_inputs: usize, table: &[T], value: T) -> Vec<Cube> {
+    let mut result = Vec::new();
+    split_table(num_inputs, table, 0, num_inputs, &value, &mut result);
+    result
+}
+
+fn split_table<T: PartialEq>(
+    num_inputs: usize,
+    table: &[T],
+    start: usize,
+    free: usize,
+    value: &T,
+    result: &mut Vec<Cube>,
+) {
+    let block = &table[start..start + (1 << free)];
+    if block.iter().all(|b| b == value) {
+        let mask = full_mask(num_inputs) & !full_mask(free);
+        result.push(Cube::new(start as u32, mask));
+    } else if block.contains(value) {
+        let half = 1 << (free - 1);
+        split_table(num_inputs, table, start, free - 1, value, result);
+        split_table(num_inputs, table, start + half, free - 1, value, result);
+    }
+}
+
+/// Expands every cube as far as possible without hitting the off-set. Cubes which are covered by an
+/// already expanded cube are dropped.
+fn expand(mut cover: Vec<Cube>, off: &[Cube]) -> Vec<Cube> {
+    // expanding large cubes first makes it more likely that small cubes get covered
+    cover.sort_by_key(|c| c.literals());
+
+    let mut result: Vec<Cube> = Vec::new();
+    for cube in cover {
+        if result.iter().any(|c| c.contains(&cube)) {
+            continue;
+        }
+
+        let mut cube = cube;
+        // raise the literals which block the fewest off-set cubes first
+        let mut literals: Vec<u32> = (0..32)
+            .map(|bit| 1 << bit)
+            .filter(|&bit| cube.mask & bit != 0)
+            .collect();
+        literals.sort_by_key(|&bit| {
+            off.iter()
+                .filter(|o| o.mask & bit != 0 && (o.value ^ cube.value) & bit != 0)
+                .count()
+        });
+
+        for bit in literals {
+            let raised = Cube::new(cube.value, cube.mask & !bit);
+            if !off.iter().any(|o| o.intersects(&raised)) {
+                cube = raised;
+            }
+        }
+
+        result.retain(|c| !cube.contains(c));
+        result.push(cube);
+    }
+
+    result
+}
+
+/// Removes cubes which are covered by the rest of the cover and the don't-care-set.
+///
+/// Cubes which are not covered by the other cubes are relatively essential and always stay. From the
+/// partially redundant rest the smallest subset which still covers the function is kept.
+fn irredundant(cover: Vec<Cube>, dc: &[Cube], vars: u32) -> Vec<Cube> {
+    let mut essential = Vec::new();
+    let mut redundant = Vec::new();
+
+    for (i, cube) in cover.iter().enumerate() {
+        let others: Vec<Cube> = cover
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, c)| *c)
+            .chain(dc.iter().cloned())
+            .collect();
+
+        if covers_cube(&others, cube, vars) {
+            redundant.push(*cube);
+        } else {
+            essential.push(*cube);
+        }
+    }
+
+    let base: Vec<Cube> = essential.iter().chain(dc.iter()).cloned().collect();
+    redundant.retain(|cube| !covers_cube(&base, cube, vars));
+
+    let is_cover = |keep: &[Cube]| {
+        let cover: Vec<Cube> = base.iter().chain(keep.iter()).cloned().collect();
+        redundant
+            .iter()
+            .all(|cube| keep.contains(cube) || covers_cube(&cover, cube, vars))
+    };
+
+    let keep = if redundant.len() <= IRREDUNDANT_MAX_SEARCH {
+        (0..=redundant.len())
+            .find_map(|size| smallest_subset(&redundant, size, &is_cover))
+            .unwrap_or_else(|| redundant.clone())
+    } else {
+        // greedy: drop one cube after another as long as the rest still covers it
+        let mut keep = redundant.clone();
+        let mut i = 0;
+        while i < keep.len() {
+            let rest: Vec<Cube> = base
+                .iter()
+                .chain(
+                    keep.iter()
+                        .enumerate()
+                        .filter(|(j, _)| *j != i)
+                        .map(|(_, c)| c),
+                )
+                .cloned()
+                .collect();
+            if covers_cube(&rest, &keep[i], vars) {
+                keep.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        keep
+    };
+
+    essential.extend(keep);
+    essential
+}
+
+/// Searches all subsets with `size` cubes and returns the first one for which `valid` holds.
+fn smallest_subset<F: Fn(&[Cube]) -> bool>(
+    cubes: &[Cube],
+    size: usize,
+    valid: &F,
+) -> Option<Vec<Cube>> {
+    fn search<F: Fn(&[Cube]) -> bool>(
+        cubes: &[Cube],
+        start: usize,
+        size: usize,
+        current: &mut Vec<Cube>,
+        valid: &F,
+    ) -> bool {
+        if current.len() == size {
+            return valid(current);
+        }
+
+        for i in start..cubes.len() {
+            current.push(cubes[i]);
+            if search(cubes, i + 1, size, current, valid) {
+                return true;
+            }
+            current.pop();
+        }
+        false
+    }
+
+    let mut current = Vec::new();
+    if search(cubes, 0, size, &mut current, valid) {
+        Some(current)
+    } else {
+        None
+    }
+}
+
+/// Last gasp tries to escape a local minimum: every cube is reduced on its own, pairs of reduced cubes
+/// which fit into one cube without hitting the off-set are merged and added to the cover before
+/// removing the redundant cubes again.
+fn last_gasp(cover: &[Cube], dc: &[Cube], off: &[Cube], vars: u32) -> Vec<Cube> {
+    if cover.len() > LAST_GASP_MAX_CUBES {
+        return cover.to_vec();
+    }
+
+    let reduced: Vec<Cube> = (0..cover.len())
+        .filter_map(|i| {
+            let others: Vec<Cube> = cover
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, c)| *c)
+                .collect();
+            reduce_cube(&cover[i], &others, dc)
+        })
+        .collect();
+
+    let mut merged = Vec::new();
+    for (i, a) in reduced.iter().enumerate() {
+        for b in reduced.iter().skip(i + 1) {
+            let cube = supercube(&[*a, *b]).unwrap();
+            if !off.iter().any(|o| o.intersects(&cube)) && !merged.contains(&cube) {
+                merged.push(cube);
+            }
+        }
+    }
+
+    if merged.is_empty() {
+        return cover.to_vec();
+    }
+
+    let mut next = cover.to_vec();
+    next.extend(expand(merged, off));
+    irredundant(next, dc, vars)
+}
+
+/// Shrinks every cube to the smallest cube which still covers the minterms no other cube covers.
+/// This moves the cover away from the local minimum found by expand.
+fn reduce(mut cover: Vec<Cube>, dc: &[Cube]) -> Vec<Cube> {
+    cover.sort_by_key(|c| std::cmp::Reverse(c.literals()));
+
+    let mut i = 0;
+    while i < cover.len() {
+        let others: Vec<Cube> = cover
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, c)| *c)
+            .collect();
+
+        match reduce_cube(&cover[i], &others, dc) {
+            Some(cube) => {
+                cover[i] = cube;
+                i += 1;
+            }
+            None => {
+                cover.remove(i);
+            }
+        }
+    }
+
+    cover
+}
+
+/// Returns the smallest cube which covers the part of `cube` not covered by `others` and the
+/// don't-care-set or none if nothing is left.
+fn reduce_cube(cube: &Cube, others: &[Cube], dc: &[Cube]) -> Option<Cube> {
+    let cofactor: Vec<Cube> = others
+        .iter()
+        .chain(dc.iter())
+        .filter_map(|c| c.cofactor(cube))
+        .collect();
+
+    let sc = supercube(&complement(&cofactor))?;
+    Some(Cube::new(cube.value | sc.value, cube.mask | sc.mask))
+}
+
+/// Checks if `cube` is covered by the union of `cover`.
+fn covers_cube(cover: &[Cube], cube: &Cube, vars: u32) -> bool {
+    let cofactor: Vec<Cube> = cover.iter().filter_map(|c| c.cofactor(cube)).collect();
+    tautology(&cofactor, vars & !cube.mask)
+}
+
+/// Checks if the cover is true for every assignment of the variables in `vars`.
+fn tautology(cover: &[Cube], vars: u32) -> bool {
+    if cover.iter().any(|c| c.mask == 0) {
+        return true;
+    } else if cover.is_empty() {
+        return false;
+    }
+
+    // a cover with less minterms than the space can't be a tautology
+    let space = vars.count_ones();
+    let minterms: u64 = cover.iter().map(|c| 1u64 << (space - c.literals())).sum();
+    if minterms < 1u64 << space {
+        return false;
+    }
+
+    let bit = split_variable(cover);
+    let (low, high) = split(cover, bit);
+    tautology(&low, vars & !bit) && tautology(&high, vars & !bit)
+}
+
+/// Computes the complement of a cover.
+fn complement(cover: &[Cube]) -> Vec<Cube> {
+    if cover.is_empty() {
+        return vec![Cube::new(0, 0)];
+    } else if cover.iter().any(|c| c.mask == 0) {
+        return Vec::new();
+    } else if cover.len() == 1 {
+        // De Morgan: !(a & b & c) = !a | (a & !b) | (a & b & !c)
+        let cube = cover[0];
+        let mut result = Vec::new();
+        let mut prefix = 0;
+        for bit in (0..32).map(|b| 1u32 << b).filter(|&b| cube.mask & b != 0) {
+            result.push(Cube::new(
+                (cube.value & prefix) | (!cube.value & bit),
+                prefix | bit,
+            ));
+            prefix |= bit;
+        }
+        return result;
+    }
+
+    let bit = split_variable(cover);
+    let (low, high) = split(cover, bit);
+
+    let mut result = Vec::new();
+    let low = complement(&low);
+    let high = complement(&high);
+
+    // cubes which appear in both halves don't depend on the split variable
+    for cube in low.iter() {
+        if high.contains(cube) {
+            result.push(*cube);
+        } else {
+            result.push(Cube::new(cube.value, cube.mask | bit));
+        }
+    }
+    for cube in high.iter() {
+        if !low.contains(cube) {
+            result.push(Cube::new(cube.value | bit, cube.mask | bit));
+        }
+    }
+
+    result
+}
+
+/// Picks the variable which appears in most cubes of the cover.
+fn split_variable(cover: &[Cube]) -> u32 {
+    (0..32)
+        .map(|b| 1u32 << b)
+        .max_by_key(|&bit| cover.iter().filter(|c| c.mask & bit != 0).count())
+        .unwrap()
+}
+
+/// Returns the cofactors of the cover for the variable `bit` being 0 and 1.
+fn split(cover: &[Cube], bit: u32) -> (Vec<Cube>, Vec<Cube>) {
+    let mut low = Vec::new();
+    let mut high = Vec::new();
+
+    for cube in cover {
+        let free = Cube::new(cube.value, cube.mask & !bit);
+        if cube.mask & bit == 0 {
+            low.push(free);
+            high.push(free);
+        } else if cube.value & bit == 0 {
+            low.push(free);
+        } else {
+            high.push(free);
+        }
+    }
+
+    (low, high)
+}
+
+/// Returns the smallest cube which contains every cube of the cover.
+fn supercube(cover: &[Cube]) -> Option<Cube> {
+    let first = cover.first()?;
+    let mut mask = first.mask;
+    for cube in cover {
+        mask &= cube.mask & !(cube.value ^ first.value);
+    }
+    Some(Cube::new(first.value, mask))
+}
+
+fn cost(cover: &[Cube]) -> (usize, u32) {
+    (cover.len(), cover.iter().map(|c| c.literals()).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cube;
+
+    fn minimize(num_inputs: usize, table: &[bool]) -> Vec<Cube> {
+        let on = super::table_cubes(num_inputs, table, true);
+        let off = super::table_cubes(num_inputs, table, false);
+        super::espresso(num_inputs, on, &[], &off)
+    }
+
+    fn covers_table(cover: &[Cube], table: &[bool]) -> bool {
+        table
+            .iter()
+            .enumerate()
+            .all(|(i, &val)| cover.iter().any(|c| c.covers(i as u32)) == val)
+    }
+
+    #[test]
+    fn table_cubes() {
+        // f = !x2 | (x2 & x1 & x0)
+        let table = vec![true, true, true, true, false, false, false, true];
+        assert_eq!(
+            super::table_cubes(3, &table, true),
+            vec![Cube::new(0, 4), Cube::new(7, 7)]
+        );
+        assert_eq!(
+            super::table_cubes(3, &table, false),
+            vec![Cube::new(4, 6), Cube::new(6, 7)]
+        );
+    }
+
+    #[test]
+    fn complement() {
+        let cover = vec![Cube::new(1, 1), Cube::new(2, 2)];
+        let result = super::complement(&cover);
+        assert_eq!(result, vec![Cube::new(0, 3)]);
+    }
+
+    #[test]
+    fn cyclic_core() {
+        let table = vec![true, true, true, false, false, true, true, true];
+        let cover = minimize(3, &table);
+
+        assert_eq!(cover.len(), 3);
+        assert!(covers_table(&cover, &table));
+    }
+
+    #[test]
+    fn wide_decoder() {
+        // 20 inputs, true if the upper 4 bits are 0xA or the lowest bit is set
+        let num_inputs = 20;
+        let table: Vec<bool> = (0..1u32 << num_inputs)
+            .map(|i| i >> 16 == 0xA || i & 1 != 0)
+            .collect();
+
+        let cover = minimize(num_inputs, &table);
+        assert_eq!(cover.len(), 2);
+        assert!(covers_table(&cover, &table));
+    }
+}
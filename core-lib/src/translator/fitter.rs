@@ -0,0 +1,358 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::circuit_config::Layout;
+use crate::translator::dnf::{Expression, GlobalTerms, Pin, Row};
+use crate::translator::fuses;
+use crate::CircuitConfig;
+
+/// What the fitter changed to make a design fit the device.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Report {
+    /// outputs whose product terms were split across an unused OLMC
+    pub splits: Vec<Split>,
+}
+
+/// An output which has more product terms than its OLMC. The fitter moved some of them to an unused
+/// OLMC and ORs its feedback into the output, so the pin of that OLMC is driven and has to stay
+/// unconnected.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Split {
+    pub output_pin: u32,
+    pub feedback_pin: u32,
+    /// number of product terms the feedback OLMC carries
+    pub terms: usize,
+}
+
+impl fmt::Display for Split {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pin {} carries {} product terms of pin {}, leave it unconnected",
+            self.feedback_pin, self.terms, self.output_pin
+        )
+    }
+}
+
+/// Splits the product terms of every output which doesn't fit its OLMC. The last term that fits is
+/// replaced with the feedback of an unused OLMC which takes the remaining terms, if these still don't
+/// fit the feedback OLMC is split again. Only the 22V10 is supported.
+pub fn split_terms(
+    exprs: &mut Vec<Expression>,
+    global: &GlobalTerms,
+    config: &CircuitConfig,
+) -> Result<Vec<Split>, String> {
+    let layout = match &config.layout {
+        Layout::Gal22V10(layout) => layout,
+        Layout::V8(_) => {
+            return Err("splitting product terms is only supported on 22V10 devices".to_string())
+        }
+    };
+    let mut splits = Vec::new();
+
+    // feedback expressions are appended, so they are split as well
+    let mut i = 0;
+    while i < exprs.len() {
+        let max = maximum_terms(exprs[i].out_pin, config)?;
+        let num_terms = exprs[i].rows.len();
+
+        if num_terms > max {
+            let rest = num_terms - (max - 1);
+            let spare = spare_olmcs(exprs, global, config)
+                .into_iter()
+                .filter(|pin| layout.columns.iter().any(|(column_pin, _)| column_pin == pin))
+                .min_by_key(|&pin| {
+                    // the smallest OLMC which takes all terms, otherwise the largest one
+                    let terms = maximum_terms(pin, config).unwrap_or(0);
+                    (terms < rest, if terms < rest { usize::MAX - terms } else { terms })
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "pin {} needs {} product terms but only {} fit and no unused OLMC is left to split them",
+                        exprs[i].out_pin, num_terms, max
+                    )
+                })?;
+
+            let rows = exprs[i].rows.split_off(max - 1);
+            exprs[i].rows.push(Row {
+                pins: vec![Pin::new(false, spare)],
+                never_true: false,
+            });
+
+            splits.push(Split {
+                output_pin: exprs[i].out_pin,
+                feedback_pin: spare,
+                terms: rows.len(),
+            });
+            exprs.push(Expression {
+                out_pin: spare,
+                enable_flip_flop: false,
+                active_low: false,
+                oe: None,
+                rows,
+            });
+        }
+
+        i += 1;
+    }
+
+    Ok(splits)
+}
+
+/// Returns the output pins which are neither driven nor used as an input.
+fn spare_olmcs(exprs: &[Expression], global: &GlobalTerms, config: &CircuitConfig) -> Vec<u32> {
+    let rows: Vec<&Row> = exprs
+        .iter()
+        .flat_map(|expr| expr.rows.iter().chain(expr.oe.iter()))
+        .chain(global.ar.iter())
+        .chain(global.sp.iter())
+        .collect();
+
+    config
+        .outputs
+        .iter()
+        .map(|&(pin, _)| pin)
+        .filter(|&pin| !exprs.iter().any(|expr| expr.out_pin == pin))
+        .filter(|&pin| {
+            !rows
+                .iter()
+                .any(|row| row.pins.iter().any(|input| input.pin_num == pin))
+        })
+        .collect()
+}
+
+/// A signal of a design as the pin assignment sees it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Signal {
+    pub name: String,
+    /// estimated number of product terms of an output, `None` if the signal is an input
+    pub terms: Option<usize>,
+    pub registered: bool,
+}
+
+/// Pin of a signal, `fixed` is false if the fitter chose the pin.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PinAssignment {
+    pub name: String,
+    pub pin: u32,
+    pub fixed: bool,
+}
+
+impl fmt::Display for PinAssignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pin {} = {}", self.pin, self.name)
+    }
+}
+
+/// Chooses a pin for every signal which isn't in `fixed`. Outputs are placed first, the ones with the
+/// most product terms on the largest OLMCs. Inputs go to the dedicated input pins and then to the OLMCs
+/// which are left. The clock pins are kept free if the design has a registered output. Returns the
+/// whole pinout sorted by pin number.
+pub fn assign_pins(
+    signals: &[Signal],
+    fixed: &HashMap<String, usize>,
+    config: &CircuitConfig,
+) -> Result<Vec<PinAssignment>, String> {
+    let mut pinout: Vec<PinAssignment> = fixed
+        .iter()
+        .map(|(name, &pin)| PinAssignment {
+            name: name.clone(),
+            pin: pin as u32,
+            fixed: true,
+        })
+        .collect();
+    let mut used: Vec<u32> = pinout.iter().map(|assignment| assignment.pin).collect();
+    if signals.iter().any(|signal| signal.registered) {
+        used.extend(clock_pins(config));
+    }
+
+    let unassigned = signals
+        .iter()
+        .filter(|signal| !fixed.contains_key(&signal.name));
+    let (mut outputs, inputs): (Vec<&Signal>, Vec<&Signal>) =
+        unassigned.partition(|signal| signal.terms.is_some());
+    outputs.sort_by_key(|signal| Reverse(signal.terms));
+
+    for signal in outputs {
+        let pin = config
+            .outputs
+            .iter()
+            .filter(|(pin, _)| !used.contains(pin))
+            .max_by_key(|&&(pin, terms)| (terms, Reverse(pin)))
+            .map(|&(pin, _)| pin)
+            .ok_or_else(|| format!("no output pin is left for {}", signal.name))?;
+
+        used.push(pin);
+        pinout.push(PinAssignment {
+            name: signal.name.clone(),
+            pin,
+            fixed: false,
+        });
+    }
+
+    let (dedicated, olmcs): (Vec<u32>, Vec<u32>) = input_pins(config)
+        .into_iter()
+        .partition(|pin| !config.outputs.iter().any(|(output, _)| output == pin));
+
+    for signal in inputs {
+        let pin = dedicated
+            .iter()
+            .chain(olmcs.iter())
+            .find(|pin| !used.contains(pin))
+            .copied()
+            .ok_or_else(|| format!("no input pin is left for {}", signal.name))?;
+
+        used.push(pin);
+        pinout.push(PinAssignment {
+            name: signal.name.clone(),
+            pin,
+            fixed: false,
+        });
+    }
+
+    pinout.sort_by_key(|assignment| assignment.pin);
+    Ok(pinout)
+}
+
+/// Returns the pins which drive the AND array in every mode of the device, sorted by pin number.
+fn input_pins(config: &CircuitConfig) -> Vec<u32> {
+    let mut pins: Vec<u32> = match &config.layout {
+        Layout::Gal22V10(layout) => layout.columns.iter().map(|&(pin, _)| pin).collect(),
+        Layout::V8(layout) => layout
+            .simple
+            .iter()
+            .map(|&(pin, _)| pin)
+            .filter(|pin| layout.complex.iter().any(|(other, _)| other == pin))
+            .filter(|pin| layout.registered.iter().any(|(other, _)| other == pin))
+            .collect(),
+    };
+    pins.sort();
+    pins
+}
+
+/// Returns the pins which are taken by the flip flops, the clock of the 22V10 is pin 1.
+fn clock_pins(config: &CircuitConfig) -> Vec<u32> {
+    match &config.layout {
+        Layout::Gal22V10(_) => vec![1],
+        Layout::V8(layout) => vec![layout.clock_pin, layout.oe_pin],
+    }
+}
+
+fn maximum_terms(pin_num: u32, config: &CircuitConfig) -> Result<usize, String> {
+    fuses::maximum_terms(pin_num, config).map(|terms| terms as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expression, Pin, Row};
+
+    fn expr(out_pin: u32, num_terms: u32) -> Expression {
+        Expression {
+            out_pin,
+            enable_flip_flop: false,
+            active_low: false,
+            oe: None,
+            rows: (0..num_terms)
+                .map(|i| Row {
+                    pins: vec![Pin::new(i % 2 == 0, i % 11 + 1)],
+                    never_true: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn assign_pins() {
+        let signal = |name: &str, terms, registered| super::Signal {
+            name: name.to_string(),
+            terms,
+            registered,
+        };
+        let config = super::CircuitConfig::gal22v10();
+        let mut fixed = std::collections::HashMap::new();
+        fixed.insert("a".to_string(), 2);
+        fixed.insert("y".to_string(), 18);
+
+        let signals = vec![
+            signal("a", None, false),
+            signal("b", None, false),
+            signal("y", Some(16), false),
+            signal("small", Some(2), true),
+            signal("large", Some(12), false),
+        ];
+        let pinout = super::assign_pins(&signals, &fixed, &config).unwrap();
+        let pin = |name: &str| pinout.iter().find(|a| a.name == name).unwrap().pin;
+
+        // the largest free OLMC is the other 16 term one, pin 1 is the clock
+        assert_eq!((pin("a"), pin("y")), (2, 18));
+        assert_eq!((pin("large"), pin("small")), (19, 17));
+        assert_eq!(pin("b"), 3);
+        assert!(pinout.windows(2).all(|pair| pair[0].pin < pair[1].pin));
+
+        // the GAL16V8 has 8 outputs
+        let signals: Vec<super::Signal> = (0..9)
+            .map(|i| signal(&format!("o{}", i), Some(1), false))
+            .collect();
+        let config = super::CircuitConfig::gal16v8();
+        assert!(super::assign_pins(&signals, &Default::default(), &config).is_err());
+    }
+
+    #[test]
+    fn inputs_on_olmcs() {
+        // 14 inputs don't fit the 12 dedicated inputs of a 22V10, the last two go to OLMC pins
+        let names: Vec<String> = (0..14).map(|i| format!("i{}", i)).collect();
+        let code = format!("y = {};", names.join(" & "));
+        let config = super::CircuitConfig::gal22v10();
+        let (table_data, pinout) = crate::parse_fitted(&code, &config).unwrap();
+        let pin = |name: &str| pinout.iter().find(|a| a.name == name).unwrap().pin;
+        assert_eq!((pin("i11"), pin("i12"), pin("i13")), (13, 14, 15));
+
+        // the OLMCs of the inputs feed back their pin
+        let jedec = crate::to_jedec(&table_data, &config, None, &Default::default()).unwrap();
+        let fuses = crate::jedec::parse(&jedec).unwrap().fuses;
+        let mut sim = crate::Simulator::new(fuses, &config).unwrap();
+        for name in names.iter() {
+            sim.set(pin(name), true).unwrap();
+        }
+        assert_eq!(sim.get(pin("y")), Some(true));
+        for name in names.iter() {
+            sim.set(pin(name), false).unwrap();
+            assert_eq!(sim.get(pin("y")), Some(false), "{} is low", name);
+            sim.set(pin(name), true).unwrap();
+        }
+    }
+
+    #[test]
+    fn split_terms() {
+        let config = super::CircuitConfig::gal22v10();
+
+        // 11 terms on pin 23 (8 terms): 7 stay and 4 go to the smallest OLMC which takes them
+        let mut exprs = vec![expr(23, 11), expr(14, 1)];
+        let splits = super::split_terms(&mut exprs, &Default::default(), &config).unwrap();
+        assert_eq!(
+            splits,
+            vec![super::Split {
+                output_pin: 23,
+                feedback_pin: 15,
+                terms: 4
+            }]
+        );
+        assert_eq!(exprs[0].rows.len(), 8);
+        assert_eq!(exprs[0].rows[7].pins, vec![Pin::new(false, 15)]);
+        assert_eq!((exprs[2].out_pin, exprs[2].rows.len()), (15, 4));
+
+        // 40 terms need a chain of feedback OLMCs
+        let mut exprs = vec![expr(23, 40)];
+        let splits = super::split_terms(&mut exprs, &Default::default(), &config).unwrap();
+        assert_eq!(splits.len(), 3);
+        assert!(exprs.iter().all(|expr| expr.rows.len() <= 16));
+
+        // pins which are used as an input can't be used
+        let mut exprs: Vec<Expression> = (14..=23).map(|pin| expr(pin, 1)).collect();
+        exprs[9] = expr(23, 9);
+        exprs.retain(|expr| expr.out_pin != 14);
+        exprs[0].rows[0].pins = vec![Pin::new(false, 14)];
+        assert!(super::split_terms(&mut exprs, &Default::default(), &config).is_err());
+    }
+}
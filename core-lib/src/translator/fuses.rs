@@ -1,10 +1,12 @@
-use crate::translator::dnf::Expression;
+use crate::circuit_config::{Gal22V10Layout, Gal22V10Olmc, Layout};
+use crate::translator::dnf::{Expression, GlobalTerms, Row};
 use crate::CircuitConfig;
 
 /// Fuses::BuildFromExpression generates a fuselist for a specific expression and outputs the result in a supplied
 ///	fuselist. It needs to know the term size and number of rows to correctly pad the fuselist with zeroes.
 fn build_from_expression(
     expr: &Expression,
+    exprs: &[Expression],
     num_rows: u32,
     row_len: u32,
     config: &CircuitConfig,
@@ -16,41 +18,59 @@ fn build_from_expression(
     }
 
     let mut fuse_list = vec![false; (num_rows * row_len) as usize];
-
-    for i in 0..row_len as usize {
-        fuse_list[i] = true;
+    let row_len = row_len as usize;
+
+    //	The first row is the output enable term, without one the output is always enabled.
+    match &expr.oe {
+        Some(oe) => build_row(oe, &mut fuse_list[..row_len], exprs, config)?,
+        None => fuse_list[..row_len]
+            .iter_mut()
+            .for_each(|fuse| *fuse = true),
     }
 
     //	Start writing DNF terms.
-    for term_index in 0..expr.rows.len() {
-        for i in 0..row_len {
-            let index = (row_len + term_index as u32 * row_len + i) as usize;
-            fuse_list[index] = true;
-        }
-
-        for pin_index in 0..expr.rows[term_index].pins.len() {
-            let pin = expr.rows[term_index].pins[pin_index].clone();
-
-            let mode = if expr.enable_flip_flop {
-                MacrocellMode::ModeRegisteredHigh
-            } else {
-                MacrocellMode::ModeCombinatorialHigh
-            };
-            let index = pin_to_index(pin.pin_num, pin.inverted, mode, config)?;
-            let index = row_len as usize + term_index * row_len as usize + index as usize;
-            fuse_list[index] = false;
-        }
+    for (term_index, row) in expr.rows.iter().enumerate() {
+        let start = row_len + term_index * row_len;
+        build_row(row, &mut fuse_list[start..start + row_len], exprs, config)?;
     }
 
     Ok(fuse_list)
 }
 
-pub fn build(exprs: &Vec<Expression>, config: &CircuitConfig) -> Result<Vec<bool>, String> {
+/// Fuses::BuildRow writes one product term to a row of the fuselist. Every fuse is blown except the
+/// ones of the pins which are part of the term, a row which is never true keeps every fuse.
+fn build_row(
+    row: &Row,
+    fuse_row: &mut [bool],
+    exprs: &[Expression],
+    config: &CircuitConfig,
+) -> Result<(), String> {
+    fuse_row.iter_mut().for_each(|fuse| *fuse = !row.never_true);
+
+    for pin in row.pins.iter() {
+        let mode = macrocell_mode(pin.pin_num, exprs);
+        let index = pin_to_index(pin.pin_num, pin.inverted, mode, config)?;
+        fuse_row[index as usize] = false;
+    }
+
+    Ok(())
+}
+
+pub fn build(
+    exprs: &Vec<Expression>,
+    global: &GlobalTerms,
+    config: &CircuitConfig,
+) -> Result<Vec<bool>, String> {
     //	Get row length for one DNF term.
     let row_len = get_row_length(config);
 
+    //	AR and SP only act on the flip flops.
+    if !global.is_empty() && !exprs.iter().any(|expr| expr.enable_flip_flop) {
+        return Err("ar and sp need at least one registered output".to_string());
+    }
+
     //	Adjust fuselist size to the fuse list size of the integrated circuit.
-    //	Set AR Fuses to zero (we don't need them as of yet)
+    //	Unused AR and SP rows stay zero so they are never true.
     let mut fuse_out = vec![false; config.num_fuses as usize];
 
     //	Start writing expressions to FuseList.
@@ -58,7 +78,7 @@ pub fn build(exprs: &Vec<Expression>, config: &CircuitConfig) -> Result<Vec<bool
         let expr_start = get_first_fuse_index(expr.out_pin, config)? as usize;
         let num_rows = maximum_terms(expr.out_pin, config)?;
 
-        let expr_buf = build_from_expression(&expr, num_rows + 1, row_len, config)?;
+        let expr_buf = build_from_expression(&expr, exprs, num_rows + 1, row_len, config)?;
 
         //	Copy ExpressionBuffer into the correct target destination in the fuse matrix.
         for i in 0..expr_buf.len() {
@@ -66,22 +86,44 @@ pub fn build(exprs: &Vec<Expression>, config: &CircuitConfig) -> Result<Vec<bool
         }
     }
 
-    //	Set SP fuses to zero because we also don't need them as of yet.
-    let last_fuse_idx = get_last_fuse_index(config.outputs.first().unwrap().0, config)?;
-    for i in 0..row_len {
-        fuse_out[(i + last_fuse_idx) as usize] = false;
+    //	Write the AR and SP rows.
+    let layout = layout(config)?;
+    for (term, start) in [(&global.ar, layout.ar), (&global.sp, layout.sp)] {
+        if let Some(row) = term {
+            let start = start as usize;
+            build_row(
+                row,
+                &mut fuse_out[start..start + row_len as usize],
+                exprs,
+                config,
+            )?;
+        }
     }
 
     //	Set S0 & S1 fuses.
     for expr in exprs {
         let mode_fuses = mode_fuse_indices(expr.out_pin, config)?;
 
-        if expr.enable_flip_flop {
-            fuse_out[(mode_fuses.0) as usize] = true;
-            fuse_out[(mode_fuses.1) as usize] = false;
-        } else {
-            fuse_out[(mode_fuses.0) as usize] = true;
-            fuse_out[(mode_fuses.1) as usize] = true;
+        fuse_out[(mode_fuses.0) as usize] = !expr.active_low;
+        fuse_out[(mode_fuses.1) as usize] = !expr.enable_flip_flop;
+    }
+
+    //	An OLMC which no expression drives is an input. Its OE row keeps every fuse so the pin stays
+    //	high impedance and S1 selects the combinatorial feedback, which reads the level of the pin
+    //	instead of /Q of the flip flop.
+    let rows = exprs
+        .iter()
+        .flat_map(|expr| expr.rows.iter().chain(expr.oe.iter()))
+        .chain(global.ar.iter())
+        .chain(global.sp.iter());
+    for row in rows {
+        for pin in row.pins.iter() {
+            if is_valid(pin.pin_num, config)
+                && macrocell_mode(pin.pin_num, exprs) == MacrocellMode::ModeNone
+            {
+                let (_, s1) = mode_fuse_indices(pin.pin_num, config)?;
+                fuse_out[s1 as usize] = true;
+            }
         }
     }
 
@@ -92,19 +134,22 @@ pub fn build(exprs: &Vec<Expression>, config: &CircuitConfig) -> Result<Vec<bool
 enum MacrocellMode {
     ModeNone,
     ModeRegisteredHigh,
-    // ModeRegisteredLow,
+    ModeRegisteredLow,
     ModeCombinatorialHigh,
-    // ModeCombinatorialLow,
+    ModeCombinatorialLow,
 }
 
-/// Fuses::Output::GetLastFuseIndex returns the last fuse of an OLMC output.
-fn get_last_fuse_index(pin_num: u32, config: &CircuitConfig) -> Result<u32, String> {
-    if !is_valid(pin_num, config) {
-        return Err("Invalid output pin".to_string());
+/// Returns the mode of the OLMC which drives the given pin, or `ModeNone` if no expression drives it.
+fn macrocell_mode(pin_num: u32, exprs: &[Expression]) -> MacrocellMode {
+    match exprs.iter().find(|expr| expr.out_pin == pin_num) {
+        Some(expr) => match (expr.enable_flip_flop, expr.active_low) {
+            (true, false) => MacrocellMode::ModeRegisteredHigh,
+            (true, true) => MacrocellMode::ModeRegisteredLow,
+            (false, false) => MacrocellMode::ModeCombinatorialHigh,
+            (false, true) => MacrocellMode::ModeCombinatorialLow,
+        },
+        None => MacrocellMode::ModeNone,
     }
-
-    Ok(get_first_fuse_index(pin_num, config)?
-        + (maximum_terms(pin_num, config)? + 1) * get_row_length(config))
 }
 
 /// Fuses::Output::IsValid checks if a given pin is an output pin:
@@ -117,27 +162,34 @@ fn is_valid(pin_num: u32, config: &CircuitConfig) -> bool {
     false
 }
 
-/// Fuses::Output::ModeFuseIndices returns the mode control fuses for a given output pin.
-/// The return value is a boolean which indicates if the fuse pair was written to the given
-/// std::pair reference. The function will only return false if the given pin number is
-/// an input pin who has no OLMC connected and therefore no control mode pin.
-fn mode_fuse_indices(pin_num: u32, config: &CircuitConfig) -> Result<(u32, u32), String> {
-    if !is_valid(pin_num, config) {
-        return Err(String::new());
+/// Returns the 22V10 layout of the config, the functions in this module can't translate other families.
+fn layout(config: &CircuitConfig) -> Result<&Gal22V10Layout, String> {
+    match &config.layout {
+        Layout::Gal22V10(layout) => Ok(layout),
+        _ => Err("the config doesn't describe a 22V10 layout".to_string()),
     }
+}
 
-    //	Get last fuse.
-    let fuse_index = get_last_fuse_index(config.outputs[0].0, config)? + get_row_length(config);
+/// Returns the layout of the OLMC which drives the given output pin.
+fn olmc(pin_num: u32, config: &CircuitConfig) -> Result<&Gal22V10Olmc, String> {
+    match layout(config)?
+        .olmcs
+        .iter()
+        .find(|olmc| olmc.pin == pin_num)
+    {
+        Some(olmc) => Ok(olmc),
+        None => Err(format!("output pin number {} has no OLMC", pin_num)),
+    }
+}
 
-    //	Get OLMC number.
-    for i in 0..config.outputs.len() {
-        if config.outputs[i].0 == pin_num {
-            let fuses_start = fuse_index + ((config.outputs.len() - 1 - i) as u32) * 2;
-            return Ok((fuses_start, fuses_start + 1));
-        }
+/// Fuses::Output::ModeFuseIndices returns the S0 and S1 mode control fuses for a given output pin.
+fn mode_fuse_indices(pin_num: u32, config: &CircuitConfig) -> Result<(u32, u32), String> {
+    if !is_valid(pin_num, config) {
+        return Err(String::new());
     }
 
-    Err(format!("output pin number {} not found in config", pin_num))
+    let olmc = olmc(pin_num, config)?;
+    Ok((olmc.s0, olmc.s1))
 }
 
 /// Fuses::Output::GetFirstFuseIndex returns the first fuse of an OLMC output.
@@ -146,25 +198,21 @@ fn get_first_fuse_index(pin_num: u32, config: &CircuitConfig) -> Result<u32, Str
         return Err("Invalid output pin".to_string());
     }
 
-    let mut fuse_index = get_row_length(config);
-
-    let mut olmc = config.outputs.last().unwrap().0;
-    while olmc > pin_num {
-        fuse_index += (maximum_terms(olmc, config)? + 1) * get_row_length(config);
-        olmc -= 1;
-    }
-    Ok(fuse_index)
+    Ok(olmc(pin_num, config)?.first_fuse)
 }
 
 /// Fuses::GetRowLength returns the length of one DNF term row.
 fn get_row_length(config: &CircuitConfig) -> u32 {
-    ((config.inputs.len() + config.special_pins.len()) * 2) as u32
+    match &config.layout {
+        Layout::Gal22V10(layout) => layout.num_columns,
+        Layout::V8(layout) => layout.num_columns,
+    }
 }
 
 /// Fuses::Output::MaximumTerms returns the maximum amount of terms an output OLMC can handle.
 /// if the function return value is -1 it means that the given pin number is not an valid output pin
 /// thus the function can't return a valid term number.
-fn maximum_terms(pin_num: u32, config: &CircuitConfig) -> Result<u32, String> {
+pub(super) fn maximum_terms(pin_num: u32, config: &CircuitConfig) -> Result<u32, String> {
     for out_pin in config.outputs.clone() {
         if out_pin.0 == pin_num {
             return Ok(out_pin.1);
@@ -175,64 +223,42 @@ fn maximum_terms(pin_num: u32, config: &CircuitConfig) -> Result<u32, String> {
 
 /// Fuses::PinToIndex converts a PIN to a fuselist row index. It takes in a PIN number and a boolean
 /// which indicates if the PIN is supposed to be inverted. It requires an extra parameter if the given
-/// PIN number correlates to an output pin. This parameter is called "Mode" and is an enum. The parameter
-/// is needed because the output from the OLMC is inverted or not inverted depending on the mode it is
-/// operating in (the mode is set through S0 and S1 fuses) so we need to know the mode to correctly pick
-/// the inverted or non inverted output from the OLMC output.
+/// PIN number correlates to an output pin. This parameter is called "Mode" and is the mode of the OLMC
+/// which drives the pin. The parameter is needed because a registered OLMC feeds back the inverted
+/// output of its flip flop, which is the complement of the pin if the output is active high, so we need
+/// to know the mode to correctly pick the inverted or non inverted output from the OLMC output.
 ///
-/// Note: The "Mode" parameter is ignored if the PIN number correlates to an input pin. If the PIN is an
-/// output pin and the parameter is not set the function will return -1 which is not a valid index.
-///
-/// Note: If the return value is -1 that means that the function couldn't find a valid index for the given
-/// parameters.
+/// Note: The "Mode" parameter is ignored if the PIN number correlates to an input pin. An output pin
+/// whose OLMC isn't used (`ModeNone`) is an input as well.
 fn pin_to_index(
     pin_num: u32,
     inverted: bool,
     mode: MacrocellMode,
     config: &CircuitConfig,
 ) -> Result<u32, String> {
-    // Handles special pins.
-    for special_pin in config.special_pins.clone() {
-        if special_pin.0 == pin_num {
-            if inverted {
-                return Ok(special_pin.1 + 1);
-            } else {
-                return Ok(special_pin.1);
-            }
-        }
-    }
+    let fuse_index = match layout(config)?
+        .columns
+        .iter()
+        .find(|(pin, _)| *pin == pin_num)
+    {
+        Some(&(_, column)) => column,
+        None => return Err(format!("pin {} can't be used as an input", pin_num)),
+    };
 
     // Handles output pins.
-    let fuse_index;
-
-    for i in 0..config.outputs.len() {
-        if config.outputs[i].0 == pin_num {
-            fuse_index = (2 + (config.outputs.len() - 1 - i) * 4) as u32;
-
-            if mode == MacrocellMode::ModeCombinatorialHigh {
-                if inverted {
-                    return Ok(fuse_index + 1);
-                } else {
-                    return Ok(fuse_index);
-                }
-            } else if mode != MacrocellMode::ModeNone {
-                if inverted {
-                    return Ok(fuse_index);
-                } else {
-                    return Ok(fuse_index + 1);
-                }
-            } else {
-                return Err(format!("unexpected mode {:?}", mode));
-            }
+    if is_valid(pin_num, config) && mode == MacrocellMode::ModeRegisteredHigh {
+        if inverted {
+            return Ok(fuse_index);
+        } else {
+            return Ok(fuse_index + 1);
         }
     }
 
     // Handles input pins.
-    fuse_index = (pin_num - 1) * 4;
     if inverted {
-        return Ok(fuse_index + 1);
+        Ok(fuse_index + 1)
     } else {
-        return Ok(fuse_index);
+        Ok(fuse_index)
     }
 }
 
@@ -243,7 +269,6 @@ mod tests {
     use crate::translator::dnf::Row;
     use crate::translator::utils::bool_to_byte;
 
-
     #[test]
     fn fuses_as_bytes_test() {
         let fuses = vec![true; 9];
@@ -305,8 +330,11 @@ mod tests {
         let expression = super::Expression {
             out_pin: 23,
             enable_flip_flop: true,
+            active_low: false,
+            oe: None,
             rows: vec![Row {
                 pins: vec![Pin::new(false, 11), Pin::new(true, 10)],
+                never_true: false,
             }],
         };
 
@@ -335,7 +363,8 @@ mod tests {
         let num_rows = super::maximum_terms(expression.out_pin, &config).unwrap();
 
         let result =
-            super::build_from_expression(&expression, num_rows + 1, row_length, &config).unwrap();
+            super::build_from_expression(&expression, &[], num_rows + 1, row_length, &config)
+                .unwrap();
 
         assert_eq!(result.len(), 396);
         let bytes = fuses_as_bytes(result);
@@ -379,86 +408,110 @@ mod tests {
             Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                active_low: false,
+                oe: None,
                 rows: vec![Row {
                     pins: vec![Pin::new(false, 11), Pin::new(true, 10)],
+                    never_true: false,
                 }],
             },
             Expression {
                 out_pin: 17,
                 enable_flip_flop: false,
+                active_low: false,
+                oe: None,
                 rows: vec![Row {
                     pins: vec![Pin::new(false, 11), Pin::new(false, 10)],
+                    never_true: false,
                 }],
             },
             Expression {
                 out_pin: 19,
                 enable_flip_flop: false,
+                active_low: false,
+                oe: None,
                 rows: vec![
                     Row {
                         pins: vec![Pin::new(true, 11), Pin::new(false, 10)],
+                        never_true: false,
                     },
                     Row {
                         pins: vec![Pin::new(false, 11), Pin::new(true, 10)],
+                        never_true: false,
                     },
                 ],
             },
             Expression {
                 out_pin: 18,
                 enable_flip_flop: false,
+                active_low: false,
+                oe: None,
                 rows: vec![
                     Row {
                         pins: vec![Pin::new(true, 11), Pin::new(false, 10)],
+                        never_true: false,
                     },
                     Row {
                         pins: vec![Pin::new(false, 11), Pin::new(true, 10)],
+                        never_true: false,
                     },
                     Row {
                         pins: vec![Pin::new(false, 11), Pin::new(false, 10)],
+                        never_true: false,
                     },
                 ],
             },
             Expression {
                 out_pin: 23,
                 enable_flip_flop: true,
+                active_low: false,
+                oe: None,
                 rows: vec![
                     Row {
                         pins: vec![Pin::new(true, 2), Pin::new(true, 3)],
+                        never_true: false,
                     },
                     Row {
                         pins: vec![Pin::new(true, 2), Pin::new(false, 3)],
+                        never_true: false,
                     },
                     Row {
                         pins: vec![Pin::new(false, 2), Pin::new(false, 3)],
+                        never_true: false,
                     },
                 ],
             },
             Expression {
-                out_pin: 23,
+                out_pin: 22,
                 enable_flip_flop: true,
+                active_low: false,
+                oe: None,
                 rows: vec![
                     Row {
                         pins: vec![Pin::new(true, 2), Pin::new(false, 3)],
+                        never_true: false,
                     },
                     Row {
                         pins: vec![Pin::new(false, 2), Pin::new(true, 3)],
+                        never_true: false,
                     },
                 ],
             },
         ];
 
-        let result = super::build(&expressions, &config).unwrap();
+        let result = super::build(&expressions, &Default::default(), &config).unwrap();
 
         assert_eq!(result.len(), 5892);
         let bytes = fuses_as_bytes(result);
         assert_eq!(
             bytes,
             vec![
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x0F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFB, 0x7F, 0xFF,
-                0xFF, 0xFF, 0xFF, 0x7B, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x0F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFB, 0xBF, 0xFF,
+                0xFF, 0xFF, 0xFF, 0xB7, 0xFF, 0xFF, 0xFF, 0xFF, 0xF7, 0x7F, 0xFF, 0xFF, 0xFF, 0xF0,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF,
+                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xB7, 0xFF, 0xFF, 0xFF, 0xFF, 0xF7, 0xBF, 0xFF, 0xFF,
+                0xFF, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
@@ -504,12 +557,169 @@ mod tests {
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0xFC,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xA0, 0xFC,
                 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             ]
         );
     }
 
+    #[test]
+    fn polarity() {
+        let config = super::CircuitConfig::gal22v10();
+        let expr = |out_pin, enable_flip_flop, active_low, pin: Pin| super::Expression {
+            out_pin,
+            enable_flip_flop,
+            active_low,
+            oe: None,
+            rows: vec![Row {
+                pins: vec![pin],
+                never_true: false,
+            }],
+        };
+        let term = |fuses: &[bool], start: usize| -> Vec<usize> {
+            (0..44).filter(|&i| !fuses[start + i]).collect()
+        };
+
+        // an active low flip flop feeds back the level of its pin
+        let fuses = super::build(
+            &vec![
+                expr(23, true, true, Pin::new(true, 23)),
+                expr(22, false, false, Pin::new(false, 23)),
+            ],
+            &Default::default(),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(term(&fuses, 88), vec![3]);
+        assert_eq!(term(&fuses, 484), vec![2]);
+        assert_eq!(&fuses[5808..5812], &[false, false, true, true]);
+
+        // an active high flip flop feeds back the complement of its pin
+        let fuses = super::build(
+            &vec![
+                expr(23, true, false, Pin::new(true, 23)),
+                expr(22, false, true, Pin::new(false, 23)),
+            ],
+            &Default::default(),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(term(&fuses, 88), vec![2]);
+        assert_eq!(term(&fuses, 484), vec![3]);
+        assert_eq!(&fuses[5808..5812], &[true, false, false, true]);
+    }
+
+    #[test]
+    fn undriven_olmc() {
+        let config = super::CircuitConfig::gal22v10();
+        // 14 = 23 & 2, no expression drives pin 23
+        let expr = super::Expression {
+            out_pin: 14,
+            enable_flip_flop: false,
+            active_low: false,
+            oe: None,
+            rows: vec![Row {
+                pins: vec![Pin::new(false, 23), Pin::new(false, 2)],
+                never_true: false,
+            }],
+        };
+        let fuses = super::build(&vec![expr], &Default::default(), &config).unwrap();
+
+        // the term reads the true column of pin 23 and pin 2
+        let first = super::get_first_fuse_index(14, &config).unwrap() as usize + 44;
+        let term: Vec<usize> = (0..44).filter(|&i| !fuses[first + i]).collect();
+        assert_eq!(term, vec![2, 4]);
+
+        // S1 of pin 23 selects the combinatorial feedback and its OE row is never true
+        assert_eq!(&fuses[5808..5810], &[false, true]);
+        assert!(fuses[44..88].iter().all(|&fuse| !fuse));
+    }
+
+    #[test]
+    fn output_enable() {
+        let config = super::CircuitConfig::gal22v10();
+        let mut expr = super::Expression {
+            out_pin: 23,
+            enable_flip_flop: false,
+            active_low: false,
+            oe: Some(Row {
+                pins: vec![Pin::new(true, 3)],
+                never_true: false,
+            }),
+            rows: vec![Row {
+                pins: vec![Pin::new(false, 2)],
+                never_true: false,
+            }],
+        };
+
+        // the OE row comes before the product terms, pin 3 is column 8
+        let fuses = super::build(&vec![expr.clone()], &Default::default(), &config).unwrap();
+        assert_eq!(
+            (0..44).filter(|&i| !fuses[44 + i]).collect::<Vec<_>>(),
+            vec![9]
+        );
+        assert_eq!(
+            (0..44).filter(|&i| !fuses[88 + i]).collect::<Vec<_>>(),
+            vec![4]
+        );
+
+        // an OE which is never true keeps every fuse of its row
+        expr.oe = Some(Row::never_true());
+        let fuses = super::build(&vec![expr.clone()], &Default::default(), &config).unwrap();
+        assert!(fuses[44..88].iter().all(|&fuse| !fuse));
+
+        expr.oe = None;
+        let fuses = super::build(&vec![expr], &Default::default(), &config).unwrap();
+        assert!(fuses[44..88].iter().all(|&fuse| fuse));
+    }
+
+    #[test]
+    fn global_terms() {
+        let config = super::CircuitConfig::gal22v10();
+        let expr = super::Expression {
+            out_pin: 23,
+            enable_flip_flop: true,
+            active_low: false,
+            oe: None,
+            rows: vec![Row {
+                pins: vec![Pin::new(false, 2)],
+                never_true: false,
+            }],
+        };
+        let global = super::GlobalTerms {
+            ar: Some(Row {
+                pins: vec![Pin::new(true, 3)],
+                never_true: false,
+            }),
+            sp: Some(Row {
+                pins: vec![Pin::new(false, 2), Pin::new(false, 3)],
+                never_true: false,
+            }),
+        };
+        let term = |fuses: &[bool], start: usize| -> Vec<usize> {
+            (0..44).filter(|&i| !fuses[start + i]).collect()
+        };
+
+        // AR is the first row of the array and SP the last one
+        let fuses = super::build(&vec![expr.clone()], &global, &config).unwrap();
+        assert_eq!(term(&fuses, 0), vec![9]);
+        assert_eq!(term(&fuses, 5764), vec![4, 8]);
+
+        // unused global rows are never true
+        let fuses = super::build(&vec![expr.clone()], &Default::default(), &config).unwrap();
+        assert!(fuses[..44]
+            .iter()
+            .chain(&fuses[5764..5808])
+            .all(|&fuse| !fuse));
+
+        // without a flip flop AR and SP have nothing to act on
+        let expr = super::Expression {
+            enable_flip_flop: false,
+            ..expr
+        };
+        assert!(super::build(&vec![expr], &global, &config).is_err());
+    }
+
     pub fn fuses_as_bytes(fuses: Vec<bool>) -> Vec<u8> {
         let mut byte = vec![false; 8];
         let mut result = Vec::new();
@@ -1,14 +1,19 @@
 // use crate::constants::OPENGAL_VERSION; could not find `constants` in the crate root ?????
+use crate::diagnostic::{self, Diagnostic, Span};
+use std::ops::Range;
+
 pub const OPENGAL_VERSION: &str = "open-gal 0.1.0";
 
 //	Needed JEDEC field identifiers ("http://www.pldtool.com/pdf/fmt_jedec.pdf").
-// const ID_NOTE: char = 'N';
-// const ID_CHECKSUM: char = 'C';
+const ID_NOTE: char = 'N';
+const ID_CHECKSUM: char = 'C';
 const ID_DEFAULT_FUSESTATE_FIELD: char = 'F';
-// const ID_FUSELIST_BINARY: char = 'L';
+const ID_FUSELIST_BINARY: char = 'L';
 // const ID_FUSELIST_HEX: char = 'K';
 const ID_VALUE: char = 'Q';
 const ID_PIN: char = 'P';
+const ID_SECURITY_FUSE: char = 'G';
+const ID_TEST_VECTOR: char = 'V';
 // const ID_DEVICETYPE: char = 'D';
 const ID_TERMINATOR: char = '*';
 
@@ -17,13 +22,48 @@ const ASCII_CTRL_STX: char = '\x02';
 const ASCII_CTRL_ETX: char = '\x03';
 
 //	Output blocksize for fuselist.
-const FUSE_BLOCKSIZE: u32 = 32;
+const FUSE_BLOCKSIZE: usize = 32;
+
+/// How the fuses are written to the `*L` fields.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct FuseList {
+    /// number of fuses of a `*L` field, WinCUPL and EasyGAL write 32
+    pub width: usize,
+    /// writes every block, otherwise only the blocks which differ from the default fuse state
+    pub full: bool,
+    /// state which is written to the `*F` field
+    pub default_fuse: DefaultFuse,
+}
+
+impl Default for FuseList {
+    fn default() -> Self {
+        Self {
+            width: FUSE_BLOCKSIZE,
+            full: false,
+            default_fuse: DefaultFuse::Zero,
+        }
+    }
+}
+
+/// The `*F` default fuse state, the blocks which only have this state can be left out.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum DefaultFuse {
+    /// `*F0` like WinCUPL and EasyGAL
+    #[default]
+    Zero,
+    /// `*F1`
+    One,
+    /// the state most of the fuses have, which gives the shortest file
+    Auto,
+}
 
 pub fn jedec(
     num_pins: u32,
     num_fuses: u32,
     fuse_states: Vec<bool>,
     head: Option<String>,
+    notes: &[String],
+    fuse_list: &FuseList,
 ) -> String {
     let mut result = String::new();
 
@@ -34,80 +74,304 @@ pub fn jedec(
     //	Comment section start.
     result.push(ASCII_CTRL_STX);
     result.push('\n');
-    result.push_str(&head);
+    result.push_str(head.trim_end_matches('\n'));
+    result.push('\n');
+    for note in notes {
+        result.push_str(&format!("{}{} {}\n", ID_TERMINATOR, ID_NOTE, note));
+    }
+
+    let default_fuse = match fuse_list.default_fuse {
+        DefaultFuse::Zero => false,
+        DefaultFuse::One => true,
+        DefaultFuse::Auto => {
+            fuse_states.iter().filter(|&&fuse| fuse).count() * 2 > fuse_states.len()
+        }
+    };
 
     //	Comment section end.
     result.push_str(&format!(
-        "{}{}{}{}\n*{}{}{}\n*G0\n*F",
-        ID_TERMINATOR, ID_VALUE, ID_PIN, num_pins, ID_VALUE, ID_DEFAULT_FUSESTATE_FIELD, num_fuses
+        "{}{}{}{}\n*{}{}{}\n*G0\n*F{}",
+        ID_TERMINATOR,
+        ID_VALUE,
+        ID_PIN,
+        num_pins,
+        ID_VALUE,
+        ID_DEFAULT_FUSESTATE_FIELD,
+        num_fuses,
+        default_fuse as u8
     ));
 
-    //	Start writing fusestates to file buffer.
-    let mut index = 0;
-    while index < (fuse_states.len() - 1) as u32 {
-        let modulus = index % FUSE_BLOCKSIZE;
-        if modulus == 1 && !block_contains_data(index, &fuse_states) {
-            index += FUSE_BLOCKSIZE - 1;
+    //	Start writing fusestates to file buffer, a block which only has the default state is left
+    //	out unless the full map is written.
+    let width = fuse_list.width.max(1);
+    for (block, states) in fuse_states.chunks(width).enumerate() {
+        if !fuse_list.full && states.iter().all(|&state| state == default_fuse) {
             continue;
-        } else if modulus == 0 && block_contains_data(index, &fuse_states) {
-            result.push_str(&format!("\n*L"));
-            result.push_str(&fill_num(5, &format!("{}", index)));
-            result.push(' ');
-        } else {
-            result.push(if fuse_states[index as usize] {
-                '1'
-            } else {
-                '0'
-            });
         }
-        index += 1;
+        result.push_str(&format!("\n{}{}", ID_TERMINATOR, ID_FUSELIST_BINARY));
+        result.push_str(&fill_num(5, &format!("{}", block * width)));
+        result.push(' ');
+        result.extend(states.iter().map(|&state| if state { '1' } else { '0' }));
     }
 
-    //	Calculate fuselist checksum.
-    let mut fuse_checksum = 0;
-    let mut fuse_index = 0;
-    while fuse_index > fuse_states.len() {
-        let mut fuse_buf = vec![false; 8];
+    //	Write fuselist checksum to file buffer.
+    result.push_str(&format!(
+        "\n*C{:04X}\n{}",
+        fuse_checksum(&fuse_states),
+        ASCII_CTRL_ETX
+    ));
+
+    //	Write the transmission checksum, the sum of every character from STX to ETX.
+    let file_checksum = transmission_checksum(&result);
+    result.push_str(&format!("{:04X}", file_checksum));
+
+    result
+}
+
+/// Content of a JEDEC file.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct JedecFile {
+    /// design specification, the text before the first field
+    pub header: String,
+    /// `*QP`, number of pins of the device
+    pub num_pins: Option<u32>,
+    /// `*QF`, number of fuses of the device
+    pub num_fuses: u32,
+    /// `*F`, state of the fuses which are not in a `*L` field
+    pub default_fuse: Option<bool>,
+    /// state of every fuse, `true` is a `1` in the file
+    pub fuses: Vec<bool>,
+    /// `*C`, the fuse checksum
+    pub fuse_checksum: Option<u16>,
+    /// `*N` fields
+    pub notes: Vec<String>,
+    /// `*G`, the security fuse
+    pub security_fuse: Option<bool>,
+    /// `*V` fields, the number of the vector and its pin states
+    pub test_vectors: Vec<(u32, String)>,
+    /// the checksum after the end of text character, `0000` is a dummy which isn't checked
+    pub transmission_checksum: Option<u16>,
+}
 
-        for word_index in 0..8 {
-            if fuse_index + word_index > fuse_states.len() - 1 {
-                continue;
+/// Reads a JEDEC file. Every field is terminated by a `*`, the text before the first one is the
+/// header. Fields which don't matter for a GAL (e.g. `*D` or `*J`) are skipped. Both checksums are
+/// validated, the first malformed field or wrong checksum is returned with its position.
+pub fn parse(text: &str) -> Result<JedecFile, Diagnostic> {
+    let error = |msg: &str, range: Option<Range<usize>>| {
+        Diagnostic::error(
+            diagnostic::JEDEC,
+            msg,
+            range.map(|range| Span::new(text, range)),
+        )
+    };
+
+    let start = text.find(ASCII_CTRL_STX).map_or(0, |index| index + 1);
+    let end = text[start..]
+        .find(ASCII_CTRL_ETX)
+        .map_or(text.len(), |index| start + index);
+
+    let mut file = JedecFile::default();
+    let mut fuses: Vec<Option<bool>> = Vec::new();
+    let mut checksum_range = None;
+
+    let mut offset = start;
+    for (index, field) in text[start..end].split(ID_TERMINATOR).enumerate() {
+        let field_start = offset + field.len() - field.trim_start().len();
+        offset += field.len() + 1;
+        let field = field.trim();
+        let range = field_start..field_start + field.len();
+
+        if index == 0 {
+            file.header = field.to_string();
+            continue;
+        }
+        let id = match field.chars().next() {
+            Some(id) => id,
+            None => continue,
+        };
+        let value = field[id.len_utf8()..].trim();
+
+        match id {
+            ID_VALUE => {
+                let number = value.get(1..).and_then(|number| number.trim().parse().ok());
+                match (value.chars().next(), number) {
+                    (Some(ID_PIN), Some(number)) => file.num_pins = Some(number),
+                    (Some(ID_DEFAULT_FUSESTATE_FIELD), Some(number)) => {
+                        file.num_fuses = number;
+                        fuses = vec![None; number as usize];
+                    }
+                    (Some(ID_TEST_VECTOR), Some(_)) => (),
+                    (Some(ID_PIN | ID_DEFAULT_FUSESTATE_FIELD | ID_TEST_VECTOR), None) => {
+                        return Err(error(
+                            &format!("expected a number in `*{}`", field),
+                            Some(range),
+                        ))
+                    }
+                    _ => (),
+                }
             }
+            ID_DEFAULT_FUSESTATE_FIELD => file.default_fuse = Some(bit(value, &range, &error)?),
+            ID_SECURITY_FUSE => file.security_fuse = Some(bit(value, &range, &error)?),
+            ID_FUSELIST_BINARY => {
+                if fuses.is_empty() {
+                    return Err(error(
+                        "`*L` before `*QF`, the number of fuses is unknown",
+                        Some(range),
+                    ));
+                }
+                let (address, states) =
+                    value.split_at(value.find(char::is_whitespace).unwrap_or(value.len()));
+                let address: usize = address.parse().map_err(|_| {
+                    error(
+                        &format!("expected a fuse number, found `{}`", address),
+                        Some(range.clone()),
+                    )
+                })?;
 
-            fuse_buf[word_index] = fuse_states[fuse_index + word_index];
+                let states: Vec<char> = states.chars().filter(|c| !c.is_whitespace()).collect();
+                if address + states.len() > fuses.len() {
+                    return Err(error(
+                        &format!(
+                            "fuses {}..{} are out of range, the device has {} fuses",
+                            address,
+                            address + states.len(),
+                            fuses.len()
+                        ),
+                        Some(range),
+                    ));
+                }
+                for (i, state) in states.into_iter().enumerate() {
+                    fuses[address + i] = Some(bit(&state.to_string(), &range, &error)?);
+                }
+            }
+            ID_CHECKSUM => {
+                file.fuse_checksum = Some(hex(value, &range, &error)?);
+                checksum_range = Some(range);
+            }
+            ID_NOTE => file.notes.push(value.to_string()),
+            ID_TEST_VECTOR => {
+                let (number, states) =
+                    value.split_at(value.find(char::is_whitespace).unwrap_or(value.len()));
+                let number = number.parse().map_err(|_| {
+                    error(
+                        &format!("expected a vector number, found `{}`", number),
+                        Some(range.clone()),
+                    )
+                })?;
+                file.test_vectors.push((number, states.trim().to_string()));
+            }
+            id if id.is_ascii_uppercase() => (),
+            id => {
+                return Err(error(
+                    &format!("expected a field identifier, found `{}`", id),
+                    Some(range),
+                ))
+            }
         }
+    }
 
-        fuse_checksum += crate::translator::utils::bool_to_byte(&fuse_buf) as u32;
-        fuse_index += 8;
+    if fuses.is_empty() {
+        return Err(error("the file has no `*QF` field", None));
+    }
+    for (index, fuse) in fuses.iter().enumerate() {
+        match (*fuse, file.default_fuse) {
+            (Some(state), _) | (None, Some(state)) => file.fuses.push(state),
+            (None, None) => {
+                return Err(error(
+                    &format!("fuse {} has no state and there is no `*F` field", index),
+                    None,
+                ))
+            }
+        }
     }
 
-    //	Write fuselist checksum to file buffer.
+    if let (Some(expected), Some(range)) = (file.fuse_checksum, checksum_range) {
+        let actual = fuse_checksum(&file.fuses);
+        if expected != actual {
+            return Err(error(
+                &format!(
+                    "the fuse checksum is {:04X} but the fuses add up to {:04X}",
+                    expected, actual
+                ),
+                Some(range),
+            ));
+        }
+    }
 
-    result.push_str(&format!(
-        "\n*C{}\n{}",
-        fill_num(4, &format!("{:X?}", fuse_checksum)),
-        ASCII_CTRL_ETX
-    ));
+    // the transmission checksum follows the end of text character
+    if end < text.len() {
+        let value = text[end + 1..].trim_start();
+        let value_start = text.len() - value.len();
+        let value = &value[..value.len().min(4)];
+        let range = value_start..value_start + value.len();
+        let expected = hex(value, &range, &error)?;
 
-    //	Calculate checksum for complete file buffer.
-    let file_checksum = 0;
+        // without the start of text character the checksum can't be checked
+        let actual = match start {
+            0 => expected,
+            _ => transmission_checksum(&text[start - 1..=end]),
+        };
+        if expected != 0 && expected != actual {
+            return Err(error(
+                &format!(
+                    "the transmission checksum is {:04X} but the file adds up to {:04X}",
+                    expected, actual
+                ),
+                Some(range),
+            ));
+        }
+        file.transmission_checksum = Some(expected);
+    }
 
-    /*
-     *	NOTE: Transmission checksum is disabled due to having different results from WinCupl.
-     *	It is specified in the JEDEC documentation that a transmission checksum dummy value of '0000'
-     *	is valid and should be accepted. To be guaranteed to work it is set to zero
-     */
+    Ok(file)
+}
 
-    /*
-    for (uint32_t Index = 0; Index < m_FileBuffer.size(); Index++)
-        file_checksum += result.at(Index);
-    */
+/// Sum of the fuses as 8 bit words, the first fuse is the least significant bit of the first word.
+pub fn fuse_checksum(fuses: &[bool]) -> u16 {
+    fuses.chunks(8).fold(0, |sum, word| {
+        let byte = word
+            .iter()
+            .enumerate()
+            .fold(0u16, |byte, (i, &fuse)| byte | (fuse as u16) << i);
+        sum.wrapping_add(byte)
+    })
+}
 
-    //	Write file buffer checksum.
+/// Sum of every byte from the start of text to the end of text character, both included.
+pub fn transmission_checksum(text: &str) -> u16 {
+    text.bytes()
+        .fold(0, |sum, byte| sum.wrapping_add(byte as u16))
+}
 
-    result.push_str(&fill_num(4, &format!("{:X?}", file_checksum)));
+/// Reads a `0` or `1` of a field.
+fn bit(
+    value: &str,
+    range: &Range<usize>,
+    error: &impl Fn(&str, Option<Range<usize>>) -> Diagnostic,
+) -> Result<bool, Diagnostic> {
+    match value {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(error(
+            &format!("expected 0 or 1, found `{}`", value),
+            Some(range.clone()),
+        )),
+    }
+}
 
-    result
+/// Reads the 4 hex digits of a checksum.
+fn hex(
+    value: &str,
+    range: &Range<usize>,
+    error: &impl Fn(&str, Option<Range<usize>>) -> Diagnostic,
+) -> Result<u16, Diagnostic> {
+    match u16::from_str_radix(value, 16) {
+        Ok(number) if value.len() == 4 => Ok(number),
+        _ => Err(error(
+            &format!("expected a checksum of 4 hex digits, found `{}`", value),
+            Some(range.clone()),
+        )),
+    }
 }
 
 /// rust for std::setw(4) << std::setfill('0')
@@ -120,22 +384,6 @@ fn fill_num(len: usize, num: &str) -> String {
     result
 }
 
-/// JEDEC::BlockContainsData checks if a block of fuses contains data which needs to be written,
-/// it returns true if it finds a '1' in a block of fuses. The startindex parameter is used as
-/// a block starting point in the fuse state list.
-fn block_contains_data(index: u32, fuse_states: &Vec<bool>) -> bool {
-    if index + FUSE_BLOCKSIZE > fuse_states.len() as u32 {
-        return false;
-    }
-
-    for i in index..(index + FUSE_BLOCKSIZE) {
-        if fuse_states[i as usize] {
-            return true;
-        }
-    }
-    false
-}
-
 #[cfg(test)]
 mod tests {
 
@@ -0,0 +1,388 @@
+use crate::translator::espresso::{espresso, table_cubes};
+use std::collections::HashSet;
+
+/// With `Effort::Auto` tables with up to this many inputs are minimized exactly with
+/// Quine–McCluskey, wider tables fall back to the heuristic minimizer.
+const EXACT_MAX_INPUTS: usize = 8;
+
+/// Upper bound of search nodes for the exact cover selection. If the branch and bound search
+/// runs out of nodes the best cover found so far is used.
+const COVER_MAX_NODES: usize = 20_000;
+
+/// A product term over the inputs of a truth table. Bit `i` of `value` and `mask` refers to bit `i`
+/// of the truth table index. If a bit in `mask` is set the input is part of the term and has to have
+/// the value of the corresponding bit in `value`, otherwise the input doesn't matter.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord)]
+pub struct Cube {
+    pub value: u32,
+    pub mask: u32,
+}
+
+impl Cube {
+    pub fn new(value: u32, mask: u32) -> Self {
+        Self {
+            value: value & mask,
+            mask,
+        }
+    }
+
+    /// returns a cube which only covers the given minterm
+    pub fn minterm(index: u32, num_inputs: usize) -> Self {
+        Self::new(index, full_mask(num_inputs))
+    }
+
+    /// checks if the cube covers the given minterm
+    pub fn covers(&self, index: u32) -> bool {
+        index & self.mask == self.value
+    }
+
+    /// number of inputs which are part of the term
+    pub fn literals(&self) -> u32 {
+        self.mask.count_ones()
+    }
+
+    /// checks if both cubes have at least one minterm in common
+    pub fn intersects(&self, other: &Cube) -> bool {
+        (self.value ^ other.value) & self.mask & other.mask == 0
+    }
+
+    /// checks if every minterm of `other` is covered by this cube
+    pub fn contains(&self, other: &Cube) -> bool {
+        self.mask & other.mask == self.mask && other.value & self.mask == self.value
+    }
+
+    /// returns the cube restricted to the subspace of `other`, or none if they don't intersect
+    pub fn cofactor(&self, other: &Cube) -> Option<Cube> {
+        if self.intersects(other) {
+            Some(Cube::new(self.value, self.mask & !other.mask))
+        } else {
+            None
+        }
+    }
+}
+
+/// Effort selects the algorithm which is used to minimize the truth tables.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Effort {
+    /// exact minimization for small tables and the heuristic for wide tables
+    #[default]
+    Auto,
+    /// Quine–McCluskey, finds a cover with the minimum number of terms. Picking the cover is a branch
+    /// and bound search of at most 20 000 nodes, if a table needs more the best cover found so far is
+    /// used, which may have more terms than the minimum.
+    Exact,
+    /// Espresso, fast even for the widest tables but the cover is not guaranteed to be minimal
+    Heuristic,
+}
+
+/// Minimize returns a sum of products which covers every true entry of the given truth table.
+/// Entries whose index is listed in `dont_care` may be covered or not, whatever gives the
+/// smaller result.
+pub fn minimize(
+    num_inputs: usize,
+    table: &[bool],
+    dont_care: &[usize],
+    effort: Effort,
+) -> Vec<Cube> {
+    let mut trits: Vec<Option<bool>> = table.iter().map(|&val| Some(val)).collect();
+    for &i in dont_care {
+        trits[i] = None;
+    }
+
+    let minterms = |value: Option<bool>| -> Vec<u32> {
+        trits
+            .iter()
+            .enumerate()
+            .filter(|(_, &val)| val == value)
+            .map(|(i, _)| i as u32)
+            .collect()
+    };
+
+    let exact_search = match effort {
+        Effort::Auto => num_inputs <= EXACT_MAX_INPUTS,
+        Effort::Exact => true,
+        Effort::Heuristic => false,
+    };
+
+    let mut cover = if exact_search {
+        exact(num_inputs, &minterms(Some(true)), &minterms(None))
+    } else {
+        let on = table_cubes(num_inputs, &trits, Some(true));
+        let dc = table_cubes(num_inputs, &trits, None);
+        let off = table_cubes(num_inputs, &trits, Some(false));
+        espresso(num_inputs, on, &dc, &off)
+    };
+
+    cover.sort();
+    cover
+}
+
+/// Quine–McCluskey: generates all prime implicants of the on-set and the don't-care-set and picks
+/// the smallest set which covers the on-set.
+fn exact(num_inputs: usize, on: &[u32], dc: &[u32]) -> Vec<Cube> {
+    let care: Vec<u32> = on.iter().chain(dc.iter()).cloned().collect();
+    let primes = prime_implicants(num_inputs, &care);
+    select_cover(&primes, on)
+}
+
+fn prime_implicants(num_inputs: usize, on: &[u32]) -> Vec<Cube> {
+    let mut primes = Vec::new();
+    let mut level: HashSet<Cube> = on.iter().map(|&i| Cube::minterm(i, num_inputs)).collect();
+
+    while !level.is_empty() {
+        let mut next = HashSet::new();
+        let mut merged = HashSet::new();
+
+        for cube in level.iter() {
+            for bit in 0..num_inputs {
+                let bit = 1 << bit;
+                if cube.mask & bit == 0 || cube.value & bit != 0 {
+                    continue;
+                }
+
+                let partner = Cube::new(cube.value | bit, cube.mask);
+                if level.contains(&partner) {
+                    next.insert(Cube::new(cube.value, cube.mask & !bit));
+                    merged.insert(*cube);
+                    merged.insert(partner);
+                }
+            }
+        }
+
+        for cube in level {
+            if !merged.contains(&cube) {
+                primes.push(cube);
+            }
+        }
+        level = next;
+    }
+
+    primes
+}
+
+/// Picks essential prime implicants first and covers the remaining minterms with a branch and bound
+/// search. The search starts with a greedy solution, so it always has a valid cover to fall back to.
+fn select_cover(primes: &[Cube], on: &[u32]) -> Vec<Cube> {
+    let mut cover = Vec::new();
+    let mut uncovered: Vec<u32> = on.to_vec();
+
+    // essential prime implicants
+    loop {
+        let essential = uncovered.iter().find_map(|&m| {
+            let mut covering = primes.iter().filter(|p| p.covers(m));
+            match (covering.next(), covering.next()) {
+                (Some(&p), None) => Some(p),
+                _ => None,
+            }
+        });
+
+        match essential {
+            Some(prime) => {
+                cover.push(prime);
+                uncovered.retain(|&m| !prime.covers(m));
+            }
+            None => break,
+        }
+    }
+
+    if uncovered.is_empty() {
+        return cover;
+    }
+
+    let candidates: Vec<Cube> = primes
+        .iter()
+        .filter(|p| !cover.contains(p) && uncovered.iter().any(|&m| p.covers(m)))
+        .cloned()
+        .collect();
+
+    let mut best = greedy_cover(&candidates, &uncovered);
+    let mut nodes = 0;
+    branch_and_bound(
+        &candidates,
+        &uncovered,
+        &mut Vec::new(),
+        &mut best,
+        &mut nodes,
+    );
+
+    cover.extend(best);
+    cover
+}
+
+fn greedy_cover(candidates: &[Cube], uncovered: &[u32]) -> Vec<Cube> {
+    let mut cover = Vec::new();
+    let mut uncovered = uncovered.to_vec();
+
+    while !uncovered.is_empty() {
+        let best = candidates
+            .iter()
+            .max_by_key(|p| {
+                let count = uncovered.iter().filter(|&&m| p.covers(m)).count();
+                (count, std::cmp::Reverse(p.literals()))
+            })
+            .cloned()
+            .unwrap();
+        uncovered.retain(|&m| !best.covers(m));
+        cover.push(best);
+    }
+
+    cover
+}
+
+fn branch_and_bound(
+    candidates: &[Cube],
+    uncovered: &[u32],
+    current: &mut Vec<Cube>,
+    best: &mut Vec<Cube>,
+    nodes: &mut usize,
+) {
+    if uncovered.is_empty() {
+        if current.len() < best.len() {
+            *best = current.clone();
+        }
+        return;
+    }
+
+    *nodes += 1;
+    if current.len() + lower_bound(candidates, uncovered) >= best.len() || *nodes > COVER_MAX_NODES
+    {
+        return;
+    }
+
+    // branch on the minterm with the fewest covering candidates
+    let minterm = *uncovered
+        .iter()
+        .min_by_key(|&&m| candidates.iter().filter(|p| p.covers(m)).count())
+        .unwrap();
+
+    for prime in candidates.iter().filter(|p| p.covers(minterm)) {
+        let rest: Vec<u32> = uncovered
+            .iter()
+            .filter(|&&m| !prime.covers(m))
+            .cloned()
+            .collect();
+        current.push(*prime);
+        branch_and_bound(candidates, &rest, current, best, nodes);
+        current.pop();
+    }
+}
+
+/// Counts minterms which pairwise can't be covered by the same candidate. Every one of them needs
+/// its own term, so this is a lower bound for the number of terms which are still needed.
+fn lower_bound(candidates: &[Cube], uncovered: &[u32]) -> usize {
+    let mut independent: Vec<u32> = Vec::new();
+
+    for &minterm in uncovered {
+        let conflict = candidates
+            .iter()
+            .any(|p| p.covers(minterm) && independent.iter().any(|&m| p.covers(m)));
+        if !conflict {
+            independent.push(minterm);
+        }
+    }
+
+    independent.len()
+}
+
+pub(super) fn full_mask(num_inputs: usize) -> u32 {
+    if num_inputs >= 32 {
+        u32::MAX
+    } else {
+        (1 << num_inputs) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cube, Effort};
+
+    fn covers_table(cover: &[Cube], table: &[bool]) -> bool {
+        table
+            .iter()
+            .enumerate()
+            .all(|(i, &val)| cover.iter().any(|c| c.covers(i as u32)) == val)
+    }
+
+    #[test]
+    fn or4() {
+        let mut table = vec![true; 16];
+        table[0] = false;
+
+        let cover = super::minimize(4, &table, &[], Effort::Auto);
+        assert_eq!(
+            cover,
+            vec![
+                Cube::new(1, 1),
+                Cube::new(2, 2),
+                Cube::new(4, 4),
+                Cube::new(8, 8)
+            ]
+        );
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(super::minimize(3, &[false; 8], &[], Effort::Auto), vec![]);
+        assert_eq!(
+            super::minimize(3, &[true; 8], &[], Effort::Auto),
+            vec![Cube::new(0, 0)]
+        );
+    }
+
+    #[test]
+    fn cyclic_core() {
+        // f = sum(0, 1, 2, 5, 6, 7) has no essential prime implicants
+        let table = vec![true, true, true, false, false, true, true, true];
+        let cover = super::minimize(3, &table, &[], Effort::Auto);
+
+        assert_eq!(cover.len(), 3);
+        assert!(covers_table(&cover, &table));
+    }
+
+    #[test]
+    fn heuristic() {
+        // 14 input parity of the two lowest bits or the highest bit
+        let num_inputs = 14;
+        let table: Vec<bool> = (0..1u32 << num_inputs)
+            .map(|i| (i & 1 != 0) ^ (i & 2 != 0) || i & (1 << 13) != 0)
+            .collect();
+
+        let cover = super::minimize(num_inputs, &table, &[], Effort::Auto);
+        assert_eq!(cover.len(), 3);
+        assert!(covers_table(&cover, &table));
+    }
+
+    #[test]
+    fn effort() {
+        let table = vec![true, true, true, false, false, true, true, true];
+
+        for effort in [Effort::Exact, Effort::Heuristic] {
+            let cover = super::minimize(3, &table, &[], effort);
+            assert_eq!(cover.len(), 3);
+            assert!(covers_table(&cover, &table));
+        }
+    }
+
+    #[test]
+    fn dont_care() {
+        // seven segment decoder segment a, the codes 10 to 15 are never used
+        let table: Vec<bool> = (0..16).map(|i| ![1, 4].contains(&i) && i < 10).collect();
+        let dont_care: Vec<usize> = (10..16).collect();
+
+        for effort in [Effort::Exact, Effort::Heuristic] {
+            let cover = super::minimize(4, &table, &dont_care, effort);
+            assert_eq!(cover.len(), 4);
+            for (i, &val) in table.iter().enumerate().take(10) {
+                assert_eq!(cover.iter().any(|c| c.covers(i as u32)), val);
+            }
+        }
+
+        // a + c + bd + !b!d, without the don't cares the terms need more literals
+        let literals = |cover: Vec<Cube>| cover.iter().map(|c| c.literals()).sum::<u32>();
+        assert_eq!(
+            literals(super::minimize(4, &table, &dont_care, Effort::Exact)),
+            6
+        );
+        assert!(literals(super::minimize(4, &table, &[], Effort::Exact)) > 6);
+    }
+}
@@ -1,5 +1,11 @@
 pub mod core;
+pub mod decompile;
 mod dnf;
+mod espresso;
+pub mod fitter;
 mod fuses;
-mod jedec;
+pub mod jedec;
+pub mod minimize;
+pub mod simulator;
 mod utils;
+mod v8;
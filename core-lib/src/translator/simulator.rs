@@ -0,0 +1,277 @@
+use super::v8::ROWS_PER_OLMC;
+use crate::circuit_config::Layout;
+use crate::diagnostic::Diagnostic;
+use crate::CircuitConfig;
+use std::collections::HashMap;
+
+/// How the output buffer of an OLMC is enabled.
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Enable {
+    Always,
+    /// first fuse of the OE row
+    Row(usize),
+    /// the low active OE pin of the registered outputs of a GAL16V8 or GAL20V8
+    Pin(u32),
+}
+
+/// An OLMC which drives its pin, as its mode fuses configure it.
+#[derive(PartialEq, Debug, Clone)]
+struct Olmc {
+    pin: u32,
+    registered: bool,
+    active_high: bool,
+    /// the polarity is set in front of the flip flop and the pin is driven by `/Q` (GAL16V8 and
+    /// GAL20V8), on the 22V10 it is set behind the flip flop
+    polarity_first: bool,
+    enable: Enable,
+    /// first fuse of every enabled product term row
+    rows: Vec<usize>,
+}
+
+/// Simulates a device from its fuse map, without knowing the code it was compiled from. The AND
+/// array is evaluated fuse by fuse: an intact fuse (`false`) connects its column to the product term.
+///
+/// Registered OLMCs feed back `/Q`, combinatorial ones the level of their pin, except for the outputs
+/// of a GAL16V8 or GAL20V8 in simple mode which have no feedback. A pin which is not driven reads
+/// the level which was set with `set`, all pins start low and all flip flops reset.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Simulator {
+    fuses: Vec<bool>,
+    num_columns: usize,
+    /// pin and column of every signal of the AND array
+    columns: Vec<(u32, u32)>,
+    olmcs: Vec<Olmc>,
+    /// first fuse of the asynchronous reset and synchronous preset rows (22V10 only)
+    ar: Option<usize>,
+    sp: Option<usize>,
+    /// levels which are applied to the pins from outside
+    inputs: HashMap<u32, bool>,
+    /// `Q` of every registered OLMC
+    registers: HashMap<u32, bool>,
+    /// level of every output, `None` if the output buffer is disabled
+    outputs: HashMap<u32, Option<bool>>,
+}
+
+impl Simulator {
+    /// Reads the mode fuses of the fuse map, e.g. the one of a parsed JEDEC file.
+    pub fn new(fuses: Vec<bool>, config: &CircuitConfig) -> Result<Self, Diagnostic> {
+        if fuses.len() != config.num_fuses as usize {
+            return Err(format!(
+                "the fuse map has {} fuses but the device has {}",
+                fuses.len(),
+                config.num_fuses
+            )
+            .into());
+        }
+
+        let (num_columns, columns, olmcs, ar, sp) = match &config.layout {
+            Layout::Gal22V10(layout) => {
+                let num_columns = layout.num_columns as usize;
+                let mut olmcs = Vec::new();
+                for &(pin, terms) in config.outputs.iter() {
+                    let olmc = match layout.olmcs.iter().find(|olmc| olmc.pin == pin) {
+                        Some(olmc) => olmc,
+                        None => return Err(format!("output pin {} has no OLMC", pin).into()),
+                    };
+                    let first = olmc.first_fuse as usize;
+                    olmcs.push(Olmc {
+                        pin,
+                        registered: !fuses[olmc.s1 as usize],
+                        active_high: fuses[olmc.s0 as usize],
+                        polarity_first: false,
+                        enable: Enable::Row(first),
+                        rows: (1..=terms as usize)
+                            .map(|i| first + i * num_columns)
+                            .collect(),
+                    });
+                }
+                let (ar, sp) = (Some(layout.ar as usize), Some(layout.sp as usize));
+                (num_columns, layout.columns.clone(), olmcs, ar, sp)
+            }
+            Layout::V8(layout) => {
+                // SYN and AC0 select the mode and with it the columns of the feedback: simple
+                // (1, 0), complex (1, 1) or registered (0, 1)
+                let syn = fuses[layout.syn as usize];
+                let ac0 = fuses[layout.ac0 as usize];
+                let columns = match (syn, ac0) {
+                    (true, false) => &layout.simple,
+                    (true, true) => &layout.complex,
+                    (false, true) => &layout.registered,
+                    (false, false) => {
+                        return Err("the SYN and AC0 fuses select no mode".to_string().into())
+                    }
+                };
+                let num_columns = layout.num_columns as usize;
+                // the product term disable fuse of an unused row is cleared
+                let rows = |first: usize, last: usize| -> Vec<usize> {
+                    (first..last)
+                        .filter(|&row| fuses[layout.ptd as usize + row])
+                        .map(|row| row * num_columns)
+                        .collect()
+                };
+
+                let mut olmcs = Vec::new();
+                for (olmc, &pin) in layout.olmc_pins.iter().enumerate() {
+                    let ac1 = fuses[layout.ac1 as usize + olmc];
+                    let first = olmc * ROWS_PER_OLMC;
+                    let last = first + ROWS_PER_OLMC;
+                    let (registered, enable, rows) = match (ac0, ac1) {
+                        // simple mode, the OLMC is an input
+                        (false, true) => continue,
+                        (false, false) => (false, Enable::Always, rows(first, last)),
+                        // a registered output in registered mode, enabled by the OE pin
+                        (true, false) if !syn => {
+                            (true, Enable::Pin(layout.oe_pin), rows(first, last))
+                        }
+                        // a combinatorial output in complex or registered mode, the first row is
+                        // the OE term
+                        (true, _) => {
+                            let enable = match rows(first, first + 1).first() {
+                                Some(&row) => Enable::Row(row),
+                                None => continue,
+                            };
+                            (false, enable, rows(first + 1, last))
+                        }
+                    };
+                    olmcs.push(Olmc {
+                        pin,
+                        registered,
+                        active_high: fuses[layout.xor as usize + olmc],
+                        polarity_first: true,
+                        enable,
+                        rows,
+                    });
+                }
+                // in simple mode an output has no feedback, the column of its pin reads no signal
+                let columns = columns
+                    .iter()
+                    .filter(|(pin, _)| ac0 || !olmcs.iter().any(|olmc| olmc.pin == *pin))
+                    .copied()
+                    .collect();
+                (num_columns, columns, olmcs, None, None)
+            }
+        };
+
+        let registers = olmcs
+            .iter()
+            .filter(|olmc| olmc.registered)
+            .map(|olmc| (olmc.pin, false))
+            .collect();
+        let mut simulator = Self {
+            fuses,
+            num_columns,
+            columns,
+            olmcs,
+            ar,
+            sp,
+            inputs: HashMap::new(),
+            registers,
+            outputs: HashMap::new(),
+        };
+        simulator.settle()?;
+        Ok(simulator)
+    }
+
+    /// Applies a level to a pin from outside. An output only reads it while it doesn't drive its
+    /// pin.
+    pub fn set(&mut self, pin: u32, level: bool) -> Result<(), Diagnostic> {
+        self.inputs.insert(pin, level);
+        self.settle().map_err(Diagnostic::from)
+    }
+
+    /// A rising edge of the clock, every flip flop stores the sum of its product terms. The
+    /// synchronous preset sets all of them.
+    pub fn clock(&mut self) -> Result<(), Diagnostic> {
+        let values = self.column_values();
+        let preset = self.sp.is_some_and(|row| self.product(row, &values));
+
+        for olmc in self.olmcs.iter().filter(|olmc| olmc.registered) {
+            let sum = olmc.rows.iter().any(|&row| self.product(row, &values)) || preset;
+            // the XOR of a GAL16V8 inverts the sum of an active high output, /Q drives the pin
+            let d = if olmc.polarity_first {
+                sum != olmc.active_high
+            } else {
+                sum
+            };
+            self.registers.insert(olmc.pin, d);
+        }
+        self.settle().map_err(Diagnostic::from)
+    }
+
+    /// Returns the level an output drives, `None` if the pin is no output or its output buffer is
+    /// disabled.
+    pub fn get(&self, pin: u32) -> Option<bool> {
+        self.outputs.get(&pin).copied().flatten()
+    }
+
+    /// Returns the level of a pin, the driven level of an output or the one which was set.
+    pub fn level(&self, pin: u32) -> bool {
+        match self.get(pin) {
+            Some(level) => level,
+            None => self.inputs.get(&pin).copied().unwrap_or(false),
+        }
+    }
+
+    /// Evaluates the combinatorial outputs and the asynchronous reset until nothing changes anymore.
+    /// Outputs which feed back into each other can oscillate, that is an error.
+    fn settle(&mut self) -> Result<(), String> {
+        for _ in 0..=self.olmcs.len() + 1 {
+            let values = self.column_values();
+
+            let mut registers = self.registers.clone();
+            if self.ar.is_some_and(|row| self.product(row, &values)) {
+                registers.values_mut().for_each(|q| *q = false);
+            }
+
+            let mut outputs = HashMap::new();
+            for olmc in self.olmcs.iter() {
+                let enabled = match olmc.enable {
+                    Enable::Always => true,
+                    Enable::Row(row) => self.product(row, &values),
+                    Enable::Pin(pin) => !self.inputs.get(&pin).copied().unwrap_or(false),
+                };
+                let level = match (olmc.registered, olmc.polarity_first) {
+                    (true, true) => !registers[&olmc.pin],
+                    (true, false) => registers[&olmc.pin] == olmc.active_high,
+                    (false, _) => {
+                        let sum = olmc.rows.iter().any(|&row| self.product(row, &values));
+                        sum == olmc.active_high
+                    }
+                };
+                outputs.insert(olmc.pin, enabled.then_some(level));
+            }
+
+            if outputs == self.outputs && registers == self.registers {
+                return Ok(());
+            }
+            self.outputs = outputs;
+            self.registers = registers;
+        }
+
+        Err("the outputs don't settle, a combinatorial feedback loop oscillates".to_string())
+    }
+
+    /// Returns the value of every column of the AND array, the next column of a signal holds its
+    /// complement.
+    fn column_values(&self) -> Vec<Option<bool>> {
+        let mut values = vec![None; self.num_columns];
+        for &(pin, column) in self.columns.iter() {
+            let value = match self.registers.get(&pin) {
+                // the flip flop feeds back /Q
+                Some(&q) => !q,
+                None => self.level(pin),
+            };
+            values[column as usize] = Some(value);
+            values[column as usize + 1] = Some(!value);
+        }
+        values
+    }
+
+    /// Evaluates the product term of a row. A column without a signal is never true.
+    fn product(&self, row: usize, values: &[Option<bool>]) -> bool {
+        self.fuses[row..row + self.num_columns]
+            .iter()
+            .zip(values.iter())
+            .all(|(&blown, &value)| blown || value == Some(true))
+    }
+}
@@ -1,3 +1,6 @@
+/// Packs 8 fuses into a byte, the first fuse is the most significant bit. The tests compare fuse maps
+/// as bytes.
+#[cfg(test)]
 pub fn bool_to_byte(byte: &Vec<bool>) -> u8 {
     let mut result = 0;
     for i in 0..8 {
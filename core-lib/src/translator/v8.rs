@@ -0,0 +1,466 @@
+use crate::circuit_config::V8Layout;
+use crate::translator::dnf::{Expression, Row};
+
+pub(super) const ROWS_PER_OLMC: usize = 8;
+
+/// Mode is the global operating mode of a GALxxV8 which is set by the SYN and AC0 fuses.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Mode {
+    /// every OLMC is either a combinatorial output or an input, the outputs have no OE term
+    Simple,
+    /// every OLMC is a combinatorial output with an OE term, the outer OLMCs have no feedback
+    Complex,
+    /// pin 1 is the clock and pin 11 (pin 13 on the 20V8) the output enable of the registered outputs
+    Registered,
+}
+
+impl V8Layout {
+    pub(super) fn columns(&self, mode: Mode) -> &[(u32, u32)] {
+        match mode {
+            Mode::Simple => &self.simple,
+            Mode::Complex => &self.complex,
+            Mode::Registered => &self.registered,
+        }
+    }
+
+    fn column(&self, pin_num: u32, mode: Mode) -> Option<usize> {
+        self.columns(mode)
+            .iter()
+            .find(|(pin, _)| *pin == pin_num)
+            .map(|&(_, column)| column as usize)
+    }
+
+    fn olmc(&self, pin_num: u32) -> Option<usize> {
+        self.olmc_pins.iter().position(|&pin| pin == pin_num)
+    }
+
+    /// number of product terms an output can use, the OE term is not included
+    fn maximum_terms(&self, mode: Mode, registered: bool) -> usize {
+        if mode == Mode::Simple || registered {
+            ROWS_PER_OLMC
+        } else {
+            ROWS_PER_OLMC - 1
+        }
+    }
+}
+
+/// Picks the mode with the fewest restrictions which can implement all expressions. Registered mode
+/// is only used if an output needs a flip flop, simple mode is preferred over complex mode because
+/// it offers one product term more per output.
+pub fn select_mode(exprs: &[Expression], layout: &V8Layout) -> Result<Mode, String> {
+    if exprs.iter().any(|expr| expr.enable_flip_flop) {
+        for pin in input_pins(exprs) {
+            if pin == layout.clock_pin || pin == layout.oe_pin {
+                return Err(format!(
+                    "pin {} can't be used as an input because it is the {} of the registered outputs",
+                    pin,
+                    if pin == layout.clock_pin {
+                        "clock"
+                    } else {
+                        "output enable"
+                    }
+                ));
+            }
+        }
+        return Ok(Mode::Registered);
+    }
+
+    // simple mode has no OE terms and no feedback of its outputs, a column of an output pin reads
+    // the pin of an OLMC which is used as an input
+    let inputs = input_pins(exprs);
+    let fits = |mode: Mode| {
+        inputs.iter().all(|&pin| layout.column(pin, mode).is_some())
+            && (mode != Mode::Simple || exprs.iter().all(|expr| !inputs.contains(&expr.out_pin)))
+            && exprs.iter().all(|expr| {
+                expr.rows.len() <= layout.maximum_terms(mode, false)
+                    && (mode != Mode::Simple || expr.oe.is_none())
+            })
+    };
+
+    if fits(Mode::Simple) {
+        Ok(Mode::Simple)
+    } else if fits(Mode::Complex) {
+        Ok(Mode::Complex)
+    } else {
+        Err("design doesn't fit in simple or complex mode, pins without feedback are used as inputs or an output has too many terms".to_string())
+    }
+}
+
+fn input_pins(exprs: &[Expression]) -> Vec<u32> {
+    let mut pins = Vec::new();
+    for expr in exprs {
+        for row in expr.rows.iter().chain(expr.oe.iter()) {
+            for pin in row.pins.iter() {
+                if !pins.contains(&pin.pin_num) {
+                    pins.push(pin.pin_num);
+                }
+            }
+        }
+    }
+    pins
+}
+
+/// Build generates the fuse list of a GALxxV8. The mode is chosen automatically, see `select_mode`.
+pub fn build(exprs: &[Expression], layout: &V8Layout, num_fuses: u32) -> Result<Vec<bool>, String> {
+    if num_fuses <= layout.ac0 {
+        return Err(format!(
+            "the config has {} fuses but the fuse map needs {}",
+            num_fuses,
+            layout.ac0 + 1
+        ));
+    }
+
+    let mode = select_mode(exprs, layout)?;
+    let row_len = layout.num_columns as usize;
+
+    let mut fuse_out = vec![false; num_fuses as usize];
+
+    let registered = |pin_num: u32| {
+        exprs
+            .iter()
+            .any(|expr| expr.out_pin == pin_num && expr.enable_flip_flop)
+    };
+
+    // writes a product term to a row, every fuse is blown except the ones of the pins of the term. A
+    // row which is never true keeps every fuse.
+    // Unlike on the 22V10 the XOR sits in front of the flip flop, the pin is driven by /Q through an
+    // inverting buffer and /Q is fed back, so every feedback is the level of its pin
+    let write_term = |fuses: &mut [bool], row: &Row| -> Result<(), String> {
+        fuses.iter_mut().for_each(|fuse| *fuse = !row.never_true);
+
+        for pin in row.pins.iter() {
+            let column = match layout.column(pin.pin_num, mode) {
+                Some(column) => column,
+                None => {
+                    return Err(format!(
+                        "pin {} can't be used as an input in {:?} mode",
+                        pin.pin_num, mode
+                    ))
+                }
+            };
+
+            fuses[column + pin.inverted as usize] = false;
+        }
+
+        Ok(())
+    };
+
+    for expr in exprs {
+        let olmc = match layout.olmc(expr.out_pin) {
+            Some(olmc) => olmc,
+            None => return Err(format!("pin {} is not an output pin", expr.out_pin)),
+        };
+
+        let max_terms = layout.maximum_terms(mode, expr.enable_flip_flop);
+        if expr.rows.len() > max_terms {
+            return Err(format!(
+                "Too many terms for output pin {}, {} terms are available in {:?} mode",
+                expr.out_pin, max_terms, mode
+            ));
+        }
+
+        let first_row = olmc * ROWS_PER_OLMC;
+        let rows = &mut fuse_out[first_row * row_len..(first_row + ROWS_PER_OLMC) * row_len];
+        rows.iter_mut().for_each(|fuse| *fuse = false);
+
+        // the first row is the OE term if the output has one, without an OE equation it is always enabled
+        let first_term = ROWS_PER_OLMC - max_terms;
+        match (&expr.oe, first_term) {
+            (Some(oe), 1) => write_term(&mut rows[..row_len], oe)?,
+            (Some(_), _) => {
+                return Err(format!(
+                    "output pin {} can't have an output enable equation in {:?} mode{}",
+                    expr.out_pin,
+                    mode,
+                    if mode == Mode::Registered {
+                        format!(", registered outputs are enabled by pin {}", layout.oe_pin)
+                    } else {
+                        String::new()
+                    }
+                ))
+            }
+            (None, _) => rows[..first_term * row_len]
+                .iter_mut()
+                .for_each(|fuse| *fuse = true),
+        }
+
+        for (term_index, row) in expr.rows.iter().enumerate() {
+            let start = (first_term + term_index) * row_len;
+            write_term(&mut rows[start..start + row_len], row)?;
+        }
+
+        // the XOR fuse is set for active high outputs
+        fuse_out[layout.xor as usize + olmc] = !expr.active_low;
+    }
+
+    for (olmc, &pin_num) in layout.olmc_pins.iter().enumerate() {
+        let output = exprs.iter().any(|expr| expr.out_pin == pin_num);
+        fuse_out[layout.ac1 as usize + olmc] = match mode {
+            Mode::Simple => !output,
+            Mode::Complex => true,
+            Mode::Registered => !registered(pin_num),
+        };
+    }
+
+    for i in 0..layout.olmc_pins.len() * ROWS_PER_OLMC {
+        fuse_out[layout.ptd as usize + i] = true;
+    }
+
+    fuse_out[layout.syn as usize] = mode != Mode::Registered;
+    fuse_out[layout.ac0 as usize] = mode != Mode::Simple;
+
+    Ok(fuse_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mode;
+    use crate::circuit_config::{Layout, V8Layout};
+    use crate::translator::dnf::{Expression, Pin, Row};
+    use crate::CircuitConfig;
+
+    fn v8_layout(config: CircuitConfig) -> V8Layout {
+        match config.layout {
+            Layout::V8(layout) => layout,
+            _ => panic!("expected a V8 layout"),
+        }
+    }
+
+    fn expr(out_pin: u32, enable_flip_flop: bool, rows: Vec<Vec<Pin>>) -> Expression {
+        Expression {
+            out_pin,
+            enable_flip_flop,
+            active_low: false,
+            oe: None,
+            rows: rows
+                .into_iter()
+                .map(|pins| Row {
+                    pins,
+                    never_true: false,
+                })
+                .collect(),
+        }
+    }
+
+    fn row(fuses: &[bool], row: usize) -> String {
+        row_of(fuses, row, 32)
+    }
+
+    fn row_of(fuses: &[bool], row: usize, row_len: usize) -> String {
+        fuses[row * row_len..(row + 1) * row_len]
+            .iter()
+            .map(|&b| if b { '1' } else { '0' })
+            .collect()
+    }
+
+    #[test]
+    fn select_mode() {
+        let gal16v8 = v8_layout(CircuitConfig::gal16v8());
+        let simple = vec![expr(19, false, vec![vec![Pin::new(false, 2)]])];
+        assert_eq!(super::select_mode(&simple, &gal16v8), Ok(Mode::Simple));
+
+        // pin 15 has no input in simple mode
+        let complex = vec![expr(19, false, vec![vec![Pin::new(false, 15)]])];
+        assert_eq!(super::select_mode(&complex, &gal16v8), Ok(Mode::Complex));
+
+        // an output which is fed back needs complex mode
+        let feedback = vec![
+            expr(18, false, vec![vec![Pin::new(false, 2)]]),
+            expr(
+                17,
+                false,
+                vec![vec![Pin::new(false, 18), Pin::new(false, 3)]],
+            ),
+        ];
+        assert_eq!(super::select_mode(&feedback, &gal16v8), Ok(Mode::Complex));
+
+        let registered = vec![expr(19, true, vec![vec![Pin::new(false, 2)]])];
+        assert_eq!(
+            super::select_mode(&registered, &gal16v8),
+            Ok(Mode::Registered)
+        );
+
+        // pin 1 is the clock in registered mode
+        let clock = vec![expr(19, true, vec![vec![Pin::new(false, 1)]])];
+        assert!(super::select_mode(&clock, &gal16v8).is_err());
+
+        // pin 15 needs complex mode and pin 19 has no feedback in complex mode
+        let conflict = vec![expr(
+            18,
+            false,
+            vec![vec![Pin::new(false, 15), Pin::new(false, 19)]],
+        )];
+        assert!(super::select_mode(&conflict, &gal16v8).is_err());
+    }
+
+    #[test]
+    fn simple() {
+        let gal16v8 = v8_layout(CircuitConfig::gal16v8());
+        let exprs = vec![
+            expr(
+                19,
+                false,
+                vec![vec![Pin::new(false, 2), Pin::new(false, 3)]],
+            ),
+            expr(
+                18,
+                false,
+                vec![vec![Pin::new(false, 2)], vec![Pin::new(true, 3)]],
+            ),
+        ];
+
+        let fuses = super::build(&exprs, &gal16v8, 2194).unwrap();
+
+        assert_eq!(row(&fuses, 0), "01110111111111111111111111111111");
+        assert_eq!(row(&fuses, 1), "0".repeat(32));
+        assert_eq!(row(&fuses, 8), "01111111111111111111111111111111");
+        assert_eq!(row(&fuses, 9), "11111011111111111111111111111111");
+        assert!(fuses[16 * 32..2048].iter().all(|&b| !b));
+
+        assert_eq!(
+            &fuses[2048..2056],
+            &[true, true, false, false, false, false, false, false]
+        );
+        assert_eq!(
+            &fuses[2120..2128],
+            &[false, false, true, true, true, true, true, true]
+        );
+        assert!(fuses[2128..2192].iter().all(|&b| b));
+        assert_eq!((fuses[2192], fuses[2193]), (true, false));
+    }
+
+    #[test]
+    fn complex() {
+        let gal16v8 = v8_layout(CircuitConfig::gal16v8());
+        let exprs = vec![expr(18, false, vec![vec![Pin::new(true, 15)]])];
+
+        let fuses = super::build(&exprs, &gal16v8, 2194).unwrap();
+
+        // OE term followed by the term which uses the feedback of pin 15
+        assert_eq!(row(&fuses, 8), "1".repeat(32));
+        assert_eq!(row(&fuses, 9), "11111111111111111110111111111111");
+        assert!(fuses[2120..2128].iter().all(|&b| b));
+        assert_eq!((fuses[2192], fuses[2193]), (true, true));
+    }
+
+    #[test]
+    fn registered() {
+        let gal16v8 = v8_layout(CircuitConfig::gal16v8());
+        let exprs = vec![
+            // toggle flip flop, the feedback of pin 19 is /Q which is the level of the pin
+            expr(19, true, vec![vec![Pin::new(true, 19)]]),
+            expr(12, false, vec![vec![Pin::new(false, 2)]]),
+        ];
+
+        let fuses = super::build(&exprs, &gal16v8, 2194).unwrap();
+
+        assert_eq!(row(&fuses, 0), "11101111111111111111111111111111");
+        assert_eq!(row(&fuses, 56), "1".repeat(32));
+        assert_eq!(row(&fuses, 57), "01111111111111111111111111111111");
+        assert_eq!(
+            &fuses[2120..2128],
+            &[false, true, true, true, true, true, true, true]
+        );
+        assert_eq!((fuses[2192], fuses[2193]), (false, true));
+    }
+
+    #[test]
+    fn polarity() {
+        let gal16v8 = v8_layout(CircuitConfig::gal16v8());
+        let mut toggle = expr(19, true, vec![vec![Pin::new(true, 19)]]);
+        toggle.active_low = true;
+
+        let fuses = super::build(&[toggle], &gal16v8, 2194).unwrap();
+
+        // the polarity is set in front of the flip flop, so the feedback doesn't depend on it
+        assert_eq!(row(&fuses, 0), "11101111111111111111111111111111");
+        assert!(!fuses[2048]);
+    }
+
+    #[test]
+    fn output_enable() {
+        let gal16v8 = v8_layout(CircuitConfig::gal16v8());
+        let mut exprs = vec![expr(19, false, vec![vec![Pin::new(false, 3)]])];
+        exprs[0].oe = Some(Row {
+            pins: vec![Pin::new(false, 2)],
+            never_true: false,
+        });
+
+        // simple mode has no OE rows
+        assert_eq!(super::select_mode(&exprs, &gal16v8), Ok(Mode::Complex));
+        let fuses = super::build(&exprs, &gal16v8, 2194).unwrap();
+        assert_eq!(row(&fuses, 0), "01111111111111111111111111111111");
+        assert_eq!(row(&fuses, 1), "11110111111111111111111111111111");
+
+        // registered outputs are enabled by pin 11
+        exprs[0].enable_flip_flop = true;
+        assert!(super::build(&exprs, &gal16v8, 2194).is_err());
+
+        exprs.push(expr(18, false, vec![vec![Pin::new(false, 3)]]));
+        exprs.swap(0, 1);
+        exprs[1].oe = None;
+        exprs[0].oe = Some(Row {
+            pins: vec![Pin::new(false, 2)],
+            never_true: false,
+        });
+        let fuses = super::build(&exprs, &gal16v8, 2194).unwrap();
+        assert_eq!(row(&fuses, 8), "01111111111111111111111111111111");
+    }
+
+    #[test]
+    fn too_many_terms() {
+        let gal16v8 = v8_layout(CircuitConfig::gal16v8());
+        let rows = (2..10).map(|pin| vec![Pin::new(false, pin)]).collect();
+        let mut exprs = vec![expr(19, false, rows)];
+        assert!(super::build(&exprs, &gal16v8, 2194).is_ok());
+
+        // complex mode only has seven terms per output
+        exprs[0].rows[0].pins[0] = Pin::new(false, 15);
+        assert!(super::build(&exprs, &gal16v8, 2194).is_err());
+    }
+
+    #[test]
+    fn gal20v8() {
+        let gal20v8 = v8_layout(CircuitConfig::gal20v8());
+        let exprs = vec![
+            // pin 23 is a dedicated input of the 20V8
+            expr(
+                22,
+                false,
+                vec![vec![Pin::new(false, 2), Pin::new(true, 23)]],
+            ),
+            expr(15, false, vec![vec![Pin::new(false, 3)]]),
+        ];
+
+        let fuses = super::build(&exprs, &gal20v8, 2706).unwrap();
+
+        assert_eq!(
+            row_of(&fuses, 0, 40),
+            "0111111011111111111111111111111111111111"
+        );
+        assert_eq!(
+            row_of(&fuses, 56, 40),
+            "1111011111111111111111111111111111111111"
+        );
+        assert_eq!(
+            &fuses[2560..2568],
+            &[true, false, false, false, false, false, false, true]
+        );
+        assert_eq!(
+            &fuses[2632..2640],
+            &[false, true, true, true, true, true, true, false]
+        );
+        assert!(fuses[2640..2704].iter().all(|&b| b));
+        assert_eq!((fuses[2704], fuses[2705]), (true, false));
+
+        // pin 13 is the output enable in registered mode
+        let registered = vec![expr(22, true, vec![vec![Pin::new(false, 13)]])];
+        assert!(super::select_mode(&registered, &gal20v8).is_err());
+
+        let registered = vec![expr(22, true, vec![vec![Pin::new(false, 11)]])];
+        assert_eq!(
+            super::select_mode(&registered, &gal20v8),
+            Ok(Mode::Registered)
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use crate::TableData;
+use crate::translator::minimize::{minimize, Effort};
+use crate::{GlobalTerm, TableData};
 use bool_algebra::update_values;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hash;
@@ -14,6 +15,12 @@ pub fn to_wincupl(table_data: &Vec<TableData>, head: Option<&str>) -> String {
         String::new()
     };
 
+    // global terms don't drive a pin and can't be written as a table
+    let (global, table_data): (Vec<TableData>, Vec<TableData>) = table_data
+        .iter()
+        .cloned()
+        .partition(|td| td.global.is_some());
+
     let mut in_pins = Vec::new();
     let mut out_pins = Vec::new();
     for td in table_data.clone() {
@@ -21,6 +28,12 @@ pub fn to_wincupl(table_data: &Vec<TableData>, head: Option<&str>) -> String {
         for pin in td.input_pins.clone() {
             in_pins.push(pin);
         }
+        if let Some(oe) = &td.oe {
+            in_pins.extend(oe.input_pins.iter());
+        }
+    }
+    for td in global.iter() {
+        in_pins.extend(td.input_pins.iter());
     }
     result.push_str(&pins_def(IN_PREFIX, in_pins));
     result.push_str(&pins_def(OUT_PREFIX, out_pins));
@@ -30,9 +43,79 @@ pub fn to_wincupl(table_data: &Vec<TableData>, head: Option<&str>) -> String {
         result.push_str(&table_def(td));
     }
 
+    // an output enable is a single product term, so it is written as an equation
+    for td in table_data.iter() {
+        if let Some(oe) = &td.oe {
+            result.push_str(&format!(
+                "{}{}p.oe = {};\n",
+                OUT_PREFIX,
+                td.output_pin,
+                equation(&oe.input_pins, &oe.table)
+            ));
+        }
+    }
+
+    // CUPL sets the global terms through every register, the equations of all registers have to be
+    // the same
+    let registers: Vec<u32> = table_data
+        .iter()
+        .filter(|td| td.enable_flip_flop)
+        .map(|td| td.output_pin)
+        .collect();
+    for td in global.iter() {
+        let extension = match td.global {
+            Some(GlobalTerm::AsynchronousReset) => "ar",
+            Some(GlobalTerm::SynchronousPreset) => "sp",
+            None => unreachable!(),
+        };
+        if registers.is_empty() {
+            result.push_str(&format!(
+                "/* {} has no registered output to act on */\n",
+                extension
+            ));
+        }
+        for pin in registers.iter() {
+            result.push_str(&format!(
+                "APPEND {}{}p.{} = {};\n",
+                OUT_PREFIX,
+                pin,
+                extension,
+                equation(&td.input_pins, &td.table)
+            ));
+        }
+    }
+
     result
 }
 
+/// Writes a table as a sum of products in CUPL syntax, the first input is the most significant bit
+/// of the table index.
+fn equation(input_pins: &[u32], table: &[bool]) -> String {
+    let cover = minimize(input_pins.len(), table, &[], Effort::default());
+    if cover.is_empty() {
+        return "'b'0".to_string();
+    } else if cover.iter().any(|cube| cube.mask == 0) {
+        return "'b'1".to_string();
+    }
+
+    let terms: Vec<String> = cover
+        .iter()
+        .map(|cube| {
+            let literals: Vec<String> = input_pins
+                .iter()
+                .enumerate()
+                .filter_map(|(i, pin)| {
+                    let bit = 1 << (input_pins.len() - 1 - i);
+                    let not = if cube.value & bit == 0 { "!" } else { "" };
+                    (cube.mask & bit != 0).then(|| format!("{}{}{}p", not, IN_PREFIX, pin))
+                })
+                .collect();
+            literals.join(" & ")
+        })
+        .collect();
+    terms.join(" # ")
+}
+
 fn table_def(table_data: TableData) -> String {
     let mut hasher = DefaultHasher::new();
     let mut result = String::new();
@@ -59,7 +142,7 @@ fn table_def(table_data: TableData) -> String {
     ));
     result.push_str("{\n");
 
-    result.push_str(&build_table(table_data.table));
+    result.push_str(&build_table(table_data.table, &table_data.dont_care));
     result.push_str("}\n\n");
 
     result
@@ -74,10 +157,10 @@ fn pins_def(prefix: &str, pins: Vec<u32>) -> String {
     result
 }
 
-fn build_table(table: Vec<bool>) -> String {
+fn build_table(table: Vec<bool>, dont_care: &[usize]) -> String {
     let mut result = String::new();
     let mut count = vec![false; (table.len() as f64).sqrt() as usize];
-    for b in table {
+    for (i, b) in table.into_iter().enumerate() {
         result.push_str("  'b'");
         for val in count.clone() {
             if val {
@@ -87,7 +170,9 @@ fn build_table(table: Vec<bool>) -> String {
             }
         }
         result.push_str(" => 'b'");
-        if b {
+        if dont_care.contains(&i) {
+            result.push('X');
+        } else if b {
             result.push('1');
         } else {
             result.push('0');
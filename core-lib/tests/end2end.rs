@@ -177,7 +177,6 @@ Created by EasyGal-Translator v1.0
 
 use open_gal::*;
 
-#[ignore]
 #[test]
 fn comlpex() {
     let code = r"                                     
@@ -222,24 +221,40 @@ fn comlpex() {
             output_pin: 17,
             table: vec![false, false, false, true],
             enable_flip_flop: false,
+            dont_care: vec![],
+            active_low: false,
+            oe: None,
+            global: None,
         },
         TableData {
             input_pins: vec![13, 11],
             output_pin: 19,
             table: vec![false, true, true, false],
             enable_flip_flop: false,
+            dont_care: vec![],
+            active_low: false,
+            oe: None,
+            global: None,
         },
         TableData {
             input_pins: vec![13, 11],
             output_pin: 18,
             table: vec![false, true, true, true],
             enable_flip_flop: false,
+            dont_care: vec![],
+            active_low: false,
+            oe: None,
+            global: None,
         },
         TableData {
             input_pins: vec![3, 2],
             output_pin: 23,
             table: vec![true, true, false, true],
             enable_flip_flop: true,
+            dont_care: vec![],
+            active_low: false,
+            oe: None,
+            global: None,
         },
     ];
 
@@ -305,26 +320,7 @@ fn comlpex() {
         assert_eq!(wincupl[line], wincupl_res[line]);
     }
 
-    let config = CircuitConfig::new(
-        5892,
-        24,
-        vec![
-            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-        ],
-        vec![
-            (14, 8),
-            (15, 10),
-            (16, 12),
-            (17, 14),
-            (18, 16),
-            (19, 16),
-            (20, 14),
-            (21, 12),
-            (22, 10),
-            (23, 8),
-        ],
-        vec![(13, 42)],
-    );
+    let config = devices::get("GAL22V10").unwrap();
 
     let easy_gal_jedec = vec![
         "\x02",
@@ -356,7 +352,7 @@ fn comlpex() {
         "*L03712 11111111111111111111111101010000",
         "*L05792 00000000000000001000000011111100",
         "*C4641",
-        "\x030000",
+        "\x03BB43",
     ]
     .join("\n");
 
@@ -364,7 +360,469 @@ fn comlpex() {
         &table_data,
         &config,
         Some("Created by EasyGal-Translator v1.0".to_string()),
+        &Options {
+            fuse_list: FuseList {
+                full: true,
+                ..FuseList::default()
+            },
+            ..Options::default()
+        },
     )
     .unwrap();
-    assert_eq!(jedec, easy_gal_jedec);
+    // parsing validates both checksums of the EasyGAL file
+    let easy_gal = jedec::parse(&easy_gal_jedec).unwrap();
+    assert_eq!(easy_gal.transmission_checksum, Some(0xBB43));
+    assert_eq!(
+        jedec.lines().take(6).collect::<Vec<_>>(),
+        easy_gal_jedec.lines().take(6).collect::<Vec<_>>()
+    );
+
+    // EasyGAL writes every minterm of a table as a product term while the fuse map here is
+    // minimized, so both are compared by simulating every input and the fuses after the rows as
+    // they are
+    let ours = jedec::parse(&jedec).unwrap();
+    assert_eq!(
+        jedec.lines().filter(|line| line.starts_with("*L")).count(),
+        5892 / 32 + 1
+    );
+    assert_eq!(ours.fuses[5808..], easy_gal.fuses[5808..]);
+    let mut ours = Simulator::new(ours.fuses, &config).unwrap();
+    let mut theirs = Simulator::new(easy_gal.fuses, &config).unwrap();
+    for inputs in 0..16 {
+        for (bit, pin) in [13, 11, 3, 2].into_iter().enumerate() {
+            ours.set(pin, inputs >> bit & 1 == 1).unwrap();
+            theirs.set(pin, inputs >> bit & 1 == 1).unwrap();
+        }
+        ours.clock().unwrap();
+        theirs.clock().unwrap();
+        for pin in [17, 18, 19, 23] {
+            assert_eq!(
+                ours.get(pin),
+                theirs.get(pin),
+                "pin {} for inputs {:04b}",
+                pin,
+                inputs
+            );
+        }
+    }
+}
+
+/// Compares a GAL16V8 fuse map with a reference. The order of the product terms of an OLMC doesn't
+/// matter, so the 8 rows of every OLMC are compared as a set and every other fuse as it is.
+fn assert_gal16v8_fuses(jedec: &str, reference: &str) {
+    let fuses = jedec::parse(jedec).unwrap().fuses;
+    let reference = jedec::parse(reference).unwrap().fuses;
+    assert_eq!(fuses.len(), 2194);
+    assert_eq!(reference.len(), 2194);
+
+    for olmc in 0..8 {
+        let rows = |fuses: &[bool]| {
+            let mut rows: Vec<Vec<bool>> = fuses[olmc * 256..(olmc + 1) * 256]
+                .chunks(32)
+                .map(|row| row.to_vec())
+                .collect();
+            rows.sort();
+            rows
+        };
+        assert_eq!(rows(&fuses), rows(&reference), "rows of OLMC {}", olmc);
+    }
+    assert_eq!(fuses[2048..], reference[2048..]);
+}
+
+// The references of the GAL16V8 tests are assembled by hand from the fuse map of the GAL16V8
+// datasheet: 64 rows of 32 columns, the XOR bits at 2048, the UES at 2056, AC1 at 2120, the PTD bits
+// at 2128, SYN at 2192 and AC0 at 2193.
+
+#[test]
+fn gal16v8_simple() {
+    let code = r"
+    pin 2 = a;
+    pin 3 = b;
+    pin 4 = c;
+    pin 19 = and;
+    pin 18 = or;
+    pin 17 = nb;
+
+    table(a, b -> and).fill(0) {
+        11 1
+    }
+
+    table(a, c -> or).fill(1) {
+        00 0
+    }
+
+    table(b -> nb) {
+        01
+        10
+    }";
+
+    // simple mode: SYN 1 and AC0 0, the outputs have AC1 0
+    let reference = "
+    *QP20*QF2194*G0*F0*
+    L00000 01110111111111111111111111111111*
+    L00256 01111111111111111111111111111111*
+    L00288 11111111011111111111111111111111*
+    L00512 11111011111111111111111111111111*
+    L02048 11100000*
+    L02120 00011111*
+    L02128 1111111111111111111111111111111111111111111111111111111111111111*
+    L02192 10*
+    ";
+
+    let table_data = parse(code).unwrap();
+    let jedec = to_jedec(
+        &table_data,
+        &CircuitConfig::gal16v8(),
+        None,
+        &Options::default(),
+    )
+    .unwrap();
+    assert_gal16v8_fuses(&jedec, reference);
+}
+
+#[test]
+fn gal16v8_complex() {
+    let code = r"
+    pin 2 = a;
+    pin 3 = b;
+    pin 15 = c;
+    pin 18 = y;
+    pin 17 = z;
+
+    y = a & !c;
+    z = a | b;
+    z.oe = b;";
+
+    // complex mode: SYN 1 and AC0 1, the first row of every OLMC is its output enable and pin 15 is
+    // read through the feedback of its OLMC
+    let reference = "
+    *QP20*QF2194*G0*F0*
+    L00256 11111111111111111111111111111111*
+    L00288 01111111111111111110111111111111*
+    L00512 11110111111111111111111111111111*
+    L00544 01111111111111111111111111111111*
+    L00576 11110111111111111111111111111111*
+    L02048 01100000*
+    L02120 11111111*
+    L02128 1111111111111111111111111111111111111111111111111111111111111111*
+    L02192 11*
+    ";
+
+    let table_data = parse(code).unwrap();
+    let jedec = to_jedec(
+        &table_data,
+        &CircuitConfig::gal16v8(),
+        None,
+        &Options::default(),
+    )
+    .unwrap();
+    assert_gal16v8_fuses(&jedec, reference);
+}
+
+/// Two bit counter on pin 19 and 18 with the carry on pin 12, pin 2 enables the counter.
+///
+/// Registered mode: SYN 0 and AC0 1, the registered outputs have AC1 0. The pins are driven by /Q
+/// which is also the feedback, so the feedback of a pin is its level. The combinatorial output on
+/// pin 12 uses its first row as output enable.
+const GAL16V8_REGISTERED: &str = "
+    *QP20*QF2194*G0*F0*
+    L00000 01101111111111111111111111111111*
+    L00032 10011111111111111111111111111111*
+    L00256 01011110111111111111111111111111*
+    L00288 11101101111111111111111111111111*
+    L00320 10111101111111111111111111111111*
+    L01792 11111111111111111111111111111111*
+    L01824 11011101111111111111111111111111*
+    L02048 11000001*
+    L02120 00111111*
+    L02128 1111111111111111111111111111111111111111111111111111111111111111*
+    L02192 01*
+    ";
+
+#[test]
+fn gal16v8_registered() {
+    let table_data = vec![
+        TableData::new(vec![19, 2], 19, vec![false, true, true, false], true),
+        TableData::new(
+            vec![18, 19, 2],
+            18,
+            vec![false, false, false, true, true, true, true, false],
+            true,
+        ),
+        TableData::new(vec![19, 18], 12, vec![false, false, false, true], false),
+    ];
+
+    let jedec = to_jedec(
+        &table_data,
+        &CircuitConfig::gal16v8(),
+        None,
+        &Options::default(),
+    )
+    .unwrap();
+    assert_gal16v8_fuses(&jedec, GAL16V8_REGISTERED);
+}
+
+#[test]
+fn simulate_gal16v8_registered() {
+    let fuses = jedec::parse(GAL16V8_REGISTERED).unwrap().fuses;
+    let mut sim = Simulator::new(fuses, &devices::get("GAL16V8").unwrap()).unwrap();
+
+    // the counter counts while pin 2 is set and the carry follows it
+    sim.set(2, true).unwrap();
+    let mut count = (sim.level(18) as u32) << 1 | sim.level(19) as u32;
+    for _ in 0..8 {
+        assert_eq!(sim.get(12), Some(count == 3));
+        sim.clock().unwrap();
+        count = (count + 1) % 4;
+        assert_eq!(sim.get(19), Some(count & 1 == 1));
+        assert_eq!(sim.get(18), Some(count & 2 == 2));
+    }
+    sim.set(2, false).unwrap();
+    sim.clock().unwrap();
+    assert_eq!(sim.get(19), Some(count & 1 == 1));
+    assert_eq!(sim.get(18), Some(count & 2 == 2));
+
+    // pin 11 disables the registered outputs but not the combinatorial one
+    sim.set(11, true).unwrap();
+    assert_eq!(sim.get(19), None);
+    assert_eq!(sim.get(18), None);
+    assert!(sim.get(12).is_some());
+}
+
+#[test]
+fn simulate_gal16v8_simple() {
+    let config = devices::get("GAL16V8").unwrap();
+    let (table_data, _) =
+        parse_fitted("pin 2 = a;\npin 19, 18 = y, z;\ny = a;\nz = a;", &config).unwrap();
+    let jedec = to_jedec(&table_data, &config, None, &Options::default()).unwrap();
+    let mut fuses = jedec::parse(&jedec).unwrap().fuses;
+    assert_eq!((fuses[2192], fuses[2193]), (true, false));
+
+    // the first row of pin 18 reads column 6, the one of pin 19 in simple mode, instead of pin 2.
+    // Pin 19 is an output which has no feedback in simple mode, so the row is never true.
+    assert!(!fuses[256] && fuses[262]);
+    fuses[256] = true;
+    fuses[262] = false;
+    let mut sim = Simulator::new(fuses, &config).unwrap();
+    sim.set(2, true).unwrap();
+    assert_eq!(sim.get(19), Some(true));
+    assert_eq!(sim.get(18), Some(false));
+}
+
+#[test]
+fn checksums() {
+    let checksum = |jedec: &str| {
+        let line = jedec.lines().find(|line| line.starts_with("*C")).unwrap();
+        line[2..].to_string()
+    };
+    let code = "pin 1 = a;\npin 2 = b;\npin 23 = c;\nc = a & b;";
+    let table_data = parse(code).unwrap();
+
+    // EasyGAL leaves the UES empty
+    let jedec = to_jedec(
+        &table_data,
+        &CircuitConfig::gal22v10(),
+        None,
+        &Options::default(),
+    )
+    .unwrap();
+    assert_eq!(checksum(&jedec), "0AE7");
+
+    // WinCUPL writes `00 ` to the UES
+    let options = Options {
+        ues: b"00 ".to_vec(),
+        ..Options::default()
+    };
+    let jedec = to_jedec(&table_data, &CircuitConfig::gal22v10(), None, &options).unwrap();
+    assert_eq!(checksum(&jedec), "0CA7");
+
+    // the transmission checksum is the sum of every character from STX to ETX
+    let (text, transmission) = jedec.split_at(jedec.find('\x03').unwrap() + 1);
+    let sum = text
+        .bytes()
+        .fold(0u16, |sum, byte| sum.wrapping_add(byte as u16));
+    assert_eq!(transmission, format!("{:04X}", sum));
+    assert_ne!(transmission, "0000");
+    assert!(jedec::parse(&jedec).is_ok());
+}
+
+#[test]
+fn decompile_round_trip() {
+    let designs = [
+        (
+            "pin 1, 2, 3, 4 = clk, a, b, rst;\n\
+             pin 23, 22, 21 = !y, q, z;\n\
+             y = a & b | !a & !b;\n\
+             q = a ^ q;\n\
+             q.dff;\n\
+             z = a | !b;\n\
+             z.oe = b & !y;\n\
+             ar = rst;",
+            CircuitConfig::gal22v10(),
+            44,
+        ),
+        (
+            "pin 2, 3 = a, b;\npin 19, 18 = !y, z;\ny = a & !b;\nz = a | b;",
+            CircuitConfig::gal16v8(),
+            32,
+        ),
+        (
+            "pin 2, 3 = a, en;\n\
+             pin 19, 18, 17 = q0, !q1, z;\n\
+             q0 = q0 ^ en;\n\
+             q1 = q1 ^ (q0 & en);\n\
+             q0, q1.dff;\n\
+             z = q0 & q1;\n\
+             z.oe = a;",
+            CircuitConfig::gal16v8(),
+            32,
+        ),
+    ];
+
+    // the order of the product terms depends on the order the names are read in, so the rows are
+    // compared as a set
+    let rows = |jedec: &str, width: usize| {
+        let fuses = jedec::parse(jedec).unwrap().fuses;
+        let mut rows: Vec<Vec<bool>> = fuses.chunks(width).map(|row| row.to_vec()).collect();
+        rows.sort();
+        rows
+    };
+
+    let mut sources = Vec::new();
+    for (code, config, width) in designs {
+        let (table_data, _) = parse_fitted(code, &config).unwrap();
+        let jedec = to_jedec(&table_data, &config, None, &Options::default()).unwrap();
+
+        let source = decompile(&jedec, &config).unwrap();
+        let (table_data, _) = parse_fitted(&source, &config).unwrap();
+        let again = to_jedec(&table_data, &config, None, &Options::default()).unwrap();
+        assert_eq!(rows(&again, width), rows(&jedec, width));
+        sources.push(source);
+    }
+
+    assert_eq!(
+        sources[0],
+        "pin 2 = i2;\npin 3 = i3;\npin 4 = i4;\npin 21 = o21;\npin 22 = o22;\npin 23 = !o23;\n\
+         \no21 = !i3 | i2;\no21.oe = i3 & !o23;\n\
+         \no22 = !i2 & o22 | i2 & !o22;\no22.dff;\n\
+         \no23 = !i2 & !i3 | i2 & i3;\n\
+         \nar = i4;\n"
+    );
+    assert!(sources[1].starts_with("// the device is in simple mode\n"));
+    assert!(sources[1].contains("pin 19 = !o19;\n"));
+    assert!(sources[2].starts_with(
+        "// the device is in registered mode, pin 1 is the clock and pin 11 enables the registered outputs\n"
+    ));
+    assert!(sources[2].contains("pin 18 = !o18;\n"));
+    assert!(sources[2].contains("o18.dff;\n"));
+    assert!(sources[2].contains("o17.oe = i2;\n"));
+
+    // an output which is never enabled gets an OE row with every fuse intact, so it reads back as an
+    // input
+    let config = CircuitConfig::gal22v10();
+    let (table_data, _) =
+        parse_fitted("pin 2 = a;\npin 23 = y;\ny = a;\ny.oe = 0;", &config).unwrap();
+    let jedec = to_jedec(&table_data, &config, None, &Options::default()).unwrap();
+    let source = decompile(&jedec, &config).unwrap();
+    assert!(!source.contains("o23"));
+
+    // the fuse map of a 22V10 doesn't fit a 16V8
+    let config = CircuitConfig::gal22v10();
+    let (table_data, _) =
+        parse_fitted("pin 2, 3 = a, b;\npin 23 = y;\ny = a & b;", &config).unwrap();
+    let jedec = to_jedec(&table_data, &config, None, &Options::default()).unwrap();
+    assert!(decompile(&jedec, &CircuitConfig::gal16v8()).is_err());
+}
+
+#[test]
+fn simulate() {
+    let simulator = |code: &str, config: &CircuitConfig| {
+        let (table_data, _) = parse_fitted(code, config).unwrap();
+        let jedec = to_jedec(&table_data, config, None, &Options::default()).unwrap();
+        Simulator::new(jedec::parse(&jedec).unwrap().fuses, config).unwrap()
+    };
+
+    // combinatorial outputs with OE terms, a flip flop and the asynchronous reset of a 22V10
+    let mut sim = simulator(
+        "pin 1, 2, 3, 4 = clk, a, b, rst;\n\
+         pin 23, 22, 21 = !y, q, z;\n\
+         y = a & b | !a & !b;\n\
+         q = a ^ q;\n\
+         q.dff;\n\
+         z = a | !b;\n\
+         z.oe = b & !y;\n\
+         ar = rst;",
+        &CircuitConfig::gal22v10(),
+    );
+    for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+        sim.set(2, a).unwrap();
+        sim.set(3, b).unwrap();
+        assert_eq!(sim.get(23), Some(a != b));
+        let z = if b && a != b { Some(a || !b) } else { None };
+        assert_eq!(sim.get(21), z);
+    }
+    // the flip flop toggles while a is set
+    assert_eq!(sim.get(22), Some(false));
+    for q in [true, false, true] {
+        sim.clock().unwrap();
+        assert_eq!(sim.get(22), Some(q));
+    }
+    sim.set(4, true).unwrap();
+    assert_eq!(sim.get(22), Some(false));
+    sim.clock().unwrap();
+    assert_eq!(sim.get(22), Some(false));
+
+    // a counter in the registered mode of a GAL16V8, pin 11 disables the registered outputs
+    let mut sim = simulator(
+        "pin 2, 3 = a, en;\n\
+         pin 19, 18, 17 = q0, !q1, z;\n\
+         q0 = q0 ^ en;\n\
+         q1 = q1 ^ (q0 & en);\n\
+         q0, q1.dff;\n\
+         z = q0 & q1;\n\
+         z.oe = a;",
+        &CircuitConfig::gal16v8(),
+    );
+    sim.set(2, true).unwrap();
+    sim.set(3, true).unwrap();
+    // the flip flops reset to 0 and the pins are driven by /Q, so q0 starts high and q1 low
+    for count in 1..9 {
+        assert_eq!(sim.get(19), Some(count & 1 == 1));
+        assert_eq!(sim.get(18), Some(count & 2 == 0));
+        assert_eq!(sim.get(17), Some(count & 3 == 3));
+        sim.clock().unwrap();
+    }
+    sim.set(3, false).unwrap();
+    sim.clock().unwrap();
+    assert_eq!(sim.get(19), Some(true));
+    assert_eq!(sim.get(18), Some(true));
+    sim.set(11, true).unwrap();
+    assert_eq!(sim.get(19), None);
+    assert_eq!(sim.get(18), None);
+    sim.set(2, false).unwrap();
+    assert_eq!(sim.get(17), None);
+
+    // the simple mode of a GAL16V8
+    let mut sim = simulator(
+        "pin 2, 3 = a, b;\npin 19, 18 = !y, z;\ny = a & !b;\nz = a | b;",
+        &CircuitConfig::gal16v8(),
+    );
+    for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+        sim.set(2, a).unwrap();
+        sim.set(3, b).unwrap();
+        assert_eq!(sim.get(19), Some(!(a && !b)));
+        assert_eq!(sim.get(18), Some(a || b));
+    }
+
+    // an output which inverts itself never settles
+    let mut sim = simulator(
+        "pin 2 = a;\npin 23 = y;\ny = a & !y;",
+        &CircuitConfig::gal22v10(),
+    );
+    assert_eq!(sim.get(23), Some(false));
+    assert!(sim.set(2, true).is_err());
+
+    // the fuse map has to fit the device
+    let config = CircuitConfig::gal22v10();
+    assert!(Simulator::new(vec![false; 10], &config).is_err());
 }
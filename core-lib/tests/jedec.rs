@@ -1,43 +1,108 @@
 #[cfg(test)]
 mod tests {
-    use open_gal::{to_jedec, CircuitConfig, TableData};
+    use open_gal::{
+        compile, devices, diagnostic, jedec, to_jedec, CircuitConfig, DefaultFuse, FuseList,
+        Options, Split, TableData,
+    };
+
+    /// `pin 23 = pin 1 & pin 2` compiled by WinCUPL for a 22V10. WinCUPL writes the part number to
+    /// the UES and a `*` in front of the end of text character. The fuse lines are rewritten to 32
+    /// columns, so the transmission checksum is the sum of the file as it's written here.
+    const WINCUPL_SIMPLE: &[&str] = &[
+        "\x02CUPL(WM)        5.0a  Serial# 60008009",
+        "Device          g22v10  Library DLIB-h-40-1",
+        "Created         Thu Oct 28 13:35:25 2021",
+        "Name            Name",
+        "Partno          00",
+        "Revision        01",
+        "Date            28/10/2021",
+        "Designer        Engineer",
+        "Company         None",
+        "Assembly        None",
+        "Location",
+        "*QP24",
+        "*QF5892",
+        "*G0",
+        "*F0",
+        "*L00032 00000000000011111111111111111111",
+        "*L00064 11111111111111111111111101110111",
+        "*L00096 11111111111111111111111111111111",
+        "*L00128 11110000000000000000000000000000",
+        "*L05792 00000000000000001100000000000000",
+        "*L05824 00000011000000110000001000000000",
+        "*C0CA7",
+        "*\x037984",
+    ];
+
+    fn wincupl_simple() -> (Vec<TableData>, Options) {
+        let table_data = vec![TableData {
+            input_pins: vec![1, 2],
+            output_pin: 23,
+            table: vec![false, false, false, true],
+            enable_flip_flop: false,
+            dont_care: vec![],
+            active_low: false,
+            oe: None,
+            global: None,
+        }];
+        let options = Options {
+            ues: b"00 ".to_vec(),
+            ..Options::default()
+        };
+        (table_data, options)
+    }
 
     #[test]
-    #[ignore]
     fn jedec() {
-        let jedec_res = vec![
-            "\x02",
-            "Created by open-gal 0.1.0",
-            "*QP24",
-            "*QF5892",
-            "*G0",
-            "*F0",
-            "*L00032 00000000000011111111111111111111",
-            "*L00064 11111111111111111111111111111011",
-            "*L00096 10111111111111111111111111111111",
-            "*L00128 11111111011110111111111111111111",
-            "*L00160 11111111111111111111011101111111",
-            "*L00192 11111111111111111111111111110000",
-            "*L02144 00000000000011111111111111111111",
-            "*L02176 11111111111111111111111111111111",
-            "*L02208 11111111111111111111111111111111",
-            "*L02240 01101111111111111111111111111111",
-            "*L02272 11111111111110010000000000000000",
-            "*L02880 00000000000000000000000011111111",
-            "*L02912 11111111111111111111111111111111",
-            "*L02944 11111111111111111111111111111111",
-            "*L02976 11111111111101101111111111111111",
-            "*L03008 11111111111111111111111110011111",
-            "*L03040 11111111111111111111111111111111",
-            "*L03072 11110101000000000000000000000000",
-            "*L03648 00001111111111111111111111111111",
-            "*L03680 11111111111111111111111111111111",
-            "*L03712 11111111111111111111111101010000",
-            "*L05792 00000000000000001000000011111100",
-            "*C4641",
-            "\x030000",
-        ];
+        let wincupl = jedec::parse(&WINCUPL_SIMPLE.join("\n")).unwrap();
+        let (table_data, options) = wincupl_simple();
+        let options = Options {
+            fuse_list: FuseList {
+                full: true,
+                ..FuseList::default()
+            },
+            ..options
+        };
+        let jedec = to_jedec(
+            &table_data,
+            &devices::get("GAL22V10").unwrap(),
+            None,
+            &options,
+        )
+        .unwrap();
+
+        // every line of the full fuse list matches the fuses of the WinCUPL file
+        let lines: Vec<&str> = jedec
+            .lines()
+            .filter(|line| line.starts_with("*L"))
+            .collect();
+        assert_eq!(lines.len(), 5892 / 32 + 1);
+        for line in lines {
+            let start: usize = line[2..7].parse().unwrap();
+            let fuses: String = wincupl.fuses[start..start + line.len() - 8]
+                .iter()
+                .map(|&fuse| if fuse { '1' } else { '0' })
+                .collect();
+            assert_eq!(line[8..], fuses, "fuses at {}", start);
+        }
+        assert!(jedec.contains("\n*C0CA7\n"));
+    }
+
+    #[test]
+    fn wincuple_simple() {
+        let wincupl = jedec::parse(&WINCUPL_SIMPLE.join("\n")).unwrap();
+        assert_eq!(wincupl.fuse_checksum, Some(0x0CA7));
+        assert_eq!(wincupl.transmission_checksum, Some(0x7984));
+
+        let (table_data, options) = wincupl_simple();
+        let config = devices::get("GAL22V10").unwrap();
+        let jedec = jedec::parse(&to_jedec(&table_data, &config, None, &options).unwrap()).unwrap();
+        assert_eq!(jedec.fuse_checksum, Some(0x0CA7));
+        assert_eq!(jedec.fuses, wincupl.fuses);
+    }
 
+    #[test]
+    fn minimized_or4_fits() {
         let config = CircuitConfig::new(
             5892,
             24,
@@ -58,87 +123,264 @@ mod tests {
             ],
             vec![(13, 42)],
         );
+
+        let mut table = vec![true; 16];
+        table[0] = false;
+        let table_data = vec![TableData::new(vec![1, 2, 3, 4], 23, table, false)];
+
+        assert!(to_jedec(&table_data, &config, None, &Options::default()).is_ok());
+    }
+
+    #[test]
+    fn split_terms() {
+        // the parity of five inputs needs 16 product terms, pin 23 only has 8
+        let table = (0..32u32).map(|i| i.count_ones() % 2 == 1).collect();
+        let table_data = vec![TableData::new(vec![1, 2, 3, 4, 5], 23, table, false)];
+        let config = devices::get("GAL22V10").unwrap();
+
+        assert!(to_jedec(&table_data, &config, None, &Options::default()).is_err());
+
+        let options = Options {
+            split_terms: true,
+            ..Options::default()
+        };
+        let (_, report) = compile(&table_data, &config, None, &options).unwrap();
+        assert_eq!(
+            report.splits,
+            vec![Split {
+                output_pin: 23,
+                feedback_pin: 15,
+                terms: 9,
+            }]
+        );
+
+        // only the 22V10 can split the terms through an unused OLMC
+        let table_data = vec![TableData::new(vec![2], 19, vec![false, true], false)];
+        let config = devices::get("GAL16V8").unwrap();
+        let error = compile(&table_data, &config, None, &options).unwrap_err();
+        assert_eq!(error.code, diagnostic::FIT);
+        assert_eq!(
+            error.message,
+            "splitting product terms is only supported on 22V10 devices"
+        );
+    }
+
+    #[test]
+    fn multiple_drivers() {
         let table_data = vec![
-            TableData::new(vec![10, 11], 23, vec![false, false, true, false], true),
-            TableData::new(vec![10, 11], 17, vec![false, false, false, true], false),
-            TableData::new(vec![10, 11], 19, vec![false, true, true, false], false),
-            TableData::new(vec![10, 11], 18, vec![false, true, true, true], false),
-            TableData::new(vec![3, 2], 23, vec![true, true, false, true], true),
-            TableData::new(vec![3, 2], 23, vec![false, true, true, false], true),
+            TableData::new(vec![1], 23, vec![false, true], false),
+            TableData::new(vec![2], 23, vec![false, true], false),
         ];
+        let config = devices::get("GAL22V10").unwrap();
+        assert!(to_jedec(&table_data, &config, None, &Options::default()).is_err());
+    }
 
-        let jedec = to_jedec(&table_data, &config, None).unwrap();
-        for (i, line) in jedec.lines().clone().into_iter().enumerate() {
-            assert_eq!(line, jedec_res[i]);
-        }
+    #[test]
+    fn notes() {
+        let table_data = vec![TableData::new(
+            vec![1, 2],
+            23,
+            vec![false, false, false, true],
+            false,
+        )];
+        let options = Options {
+            notes: vec!["pin 1 = a".to_string(), "pin 2 = b".to_string()],
+            ..Options::default()
+        };
+
+        let config = devices::get("GAL22V10").unwrap();
+        let jedec = to_jedec(&table_data, &config, None, &options).unwrap();
+        let lines: Vec<&str> = jedec.lines().collect();
+        assert_eq!(&lines[2..5], &["*N pin 1 = a", "*N pin 2 = b", "*QP24"]);
     }
 
     #[test]
-    #[ignore]
-    fn wincuple_simple() {
-        let table_data = vec![TableData {
-            input_pins: vec![1, 2],
-            output_pin: 23,
-            table: vec![false, false, false, true],
-            enable_flip_flop: false,
-        }];
+    fn atmel_fuse_count() {
+        let table_data = vec![TableData::new(
+            vec![1, 2],
+            23,
+            vec![false, false, false, true],
+            false,
+        )];
+        let options = Options {
+            ues: b"open-gal".to_vec(),
+            power_down: true,
+            ..Options::default()
+        };
 
-        let head = vec![
-            "CUPL(WM)        5.0a  Serial# 60008009",
-            "Device          g22v10  Library DLIB-h-40-1",
-            "Created         Thu Oct 28 13:35:25 2021",
-            "Name            Name",
-            "Partno          00",
-            "Revision        01",
-            "Date            28/10/2021",
-            "Designer        Engineer",
-            "Company         None",
-            "Assembly        None",
-            "Location",
-            "",
-        ]
-        .join("\n");
-
-        let wc_jedec = vec![
-            "*QP24",
-            "*QF5892",
-            "*G0",
-            "*F0",
-            "*L00032 00000000000011111111111111111111",
-            "*L00064 11111111111111111111111101110111",
-            "*L00096 11111111111111111111111111111111",
-            "*L00128 11110000000000000000000000000000",
-            "*L05792 00000000000000001100000000000000",
-            "*L05824 00000011000000110000001000000000",
-            "*C0CA7",
-            "*\x03800B",
-        ]
-        .join("\n");
+        let jedec = to_jedec(&table_data, &CircuitConfig::atf22v10c(), None, &options).unwrap();
+        assert!(jedec.lines().any(|line| line == "*QF5893"));
 
-        let config = CircuitConfig::new(
-            5892,
-            24,
-            vec![
-                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-            ],
-            vec![
-                (14, 8),
-                (15, 10),
-                (16, 12),
-                (17, 14),
-                (18, 16),
-                (19, 16),
-                (20, 14),
-                (21, 12),
-                (22, 10),
-                (23, 8),
-            ],
-            vec![(13, 42)],
+        let table_data = vec![TableData::new(
+            vec![2, 3],
+            19,
+            vec![false, false, false, true],
+            false,
+        )];
+        let jedec = to_jedec(&table_data, &CircuitConfig::atf16v8b(), None, &options).unwrap();
+        assert!(jedec.lines().any(|line| line == "*QF2195"));
+
+        // the lattice parts have no power-down fuse
+        assert!(to_jedec(&table_data, &CircuitConfig::gal16v8(), None, &options).is_err());
+    }
+
+    #[test]
+    fn parse() {
+        // output of EasyGAL for `c = a & b;` on the 22V10
+        let easy_gal = "\x02\nEasyGAL\n*QP24\n*QF5892\n*G0\n*F0\n\
+            *L00032 00000000000011111111111111111111\n\
+            *L00064 11111111111111111111111101110111\n\
+            *L00096 11111111111111111111111111111111\n\
+            *L00128 11110000000000000000000000000000\n\
+            *L05792 00000000000000001100000000000000\n\
+            *C0AE7\n\x030000";
+        let file = jedec::parse(easy_gal).unwrap();
+        assert_eq!(file.header, "EasyGAL");
+        assert_eq!((file.num_pins, file.num_fuses), (Some(24), 5892));
+        assert_eq!(
+            (file.default_fuse, file.security_fuse),
+            (Some(false), Some(false))
+        );
+        assert_eq!(file.fuses.len(), 5892);
+        assert_eq!(file.fuses.iter().filter(|&&fuse| fuse).count(), 88);
+        assert!(!file.fuses[32 + 11] && file.fuses[32 + 12]);
+        assert_eq!(file.fuse_checksum, Some(0x0AE7));
+
+        // fields may end with the `*` and the fuses of a `*L` may be split by spaces
+        let file =
+            "\x02\nCreated by hand*\nQP24*\nQF16*\nF0*\nL00000 10000000 01*\nC0003*\n\x030D94";
+        let file = jedec::parse(file).unwrap();
+        assert_eq!(
+            file.fuses[..10],
+            [true, false, false, false, false, false, false, false, false, true]
         );
+        assert_eq!(file.transmission_checksum, Some(0x0D94));
+        let with_notes = jedec::parse("*N pin 1 = a*QF2*F1*V0001 10*").unwrap();
+        assert_eq!(with_notes.notes, vec!["pin 1 = a"]);
+        assert_eq!(with_notes.fuses, vec![true, true]);
+        assert_eq!(with_notes.test_vectors, vec![(1, "10".to_string())]);
 
+        let error = |text: &str| {
+            let error = jedec::parse(text).unwrap_err();
+            assert_eq!(error.code, diagnostic::JEDEC);
+            (error.span.map(|span| span.line), error.message)
+        };
+        assert_eq!(
+            error("\x02\n*QF16\n*F0\n*L00000 1002\n\x030000"),
+            (Some(4), "expected 0 or 1, found `2`".to_string())
+        );
+        assert_eq!(
+            error("\x02\n*QF16\n*F0\n*L00014 111\n\x030000"),
+            (
+                Some(4),
+                "fuses 14..17 are out of range, the device has 16 fuses".to_string()
+            )
+        );
+        assert_eq!(
+            error("\x02\n*QF16\n*F0\n*L00000 1\n*C0002\n\x030000"),
+            (
+                Some(5),
+                "the fuse checksum is 0002 but the fuses add up to 0001".to_string()
+            )
+        );
+        assert_eq!(
+            error(
+                "\x02\nCreated by hand*\nQP24*\nQF16*\nF0*\nL00000 10000000 01*\nC0003*\n\x030D95"
+            ),
+            (
+                Some(8),
+                "the transmission checksum is 0D95 but the file adds up to 0D94".to_string()
+            )
+        );
+        assert_eq!(
+            error("*L00000 1*"),
+            (
+                Some(1),
+                "`*L` before `*QF`, the number of fuses is unknown".to_string()
+            )
+        );
         assert_eq!(
-            to_jedec(&table_data, &config, Some(head.clone())),
-            Ok(format!("\x02{}{}", head, wc_jedec))
+            error("*QF4*L00000 1*"),
+            (
+                None,
+                "fuse 1 has no state and there is no `*F` field".to_string()
+            )
         );
     }
+
+    #[test]
+    fn fuse_list() {
+        let table_data = vec![TableData::new(
+            vec![2, 3],
+            19,
+            vec![false, false, false, true],
+            false,
+        )];
+        let config = devices::get("GAL16V8").unwrap();
+        let write = |fuse_list: FuseList| {
+            let options = Options {
+                fuse_list,
+                ..Options::default()
+            };
+            to_jedec(&table_data, &config, None, &options).unwrap()
+        };
+        let fields = |jedec: &str, id: &str| -> Vec<String> {
+            jedec
+                .lines()
+                .filter(|line| line.starts_with(id))
+                .map(|line| line.to_string())
+                .collect()
+        };
+
+        // every fuse is written, the last block is shorter
+        let sparse = write(FuseList::default());
+        let lines = fields(&sparse, "*L");
+        assert!(lines.iter().all(|line| line.len() <= 8 + 32));
+        assert_eq!(lines.last().unwrap(), "*L02176 111111111111111110");
+
+        let full = write(FuseList {
+            full: true,
+            ..FuseList::default()
+        });
+        assert_eq!(fields(&full, "*L").len(), 2194 / 32 + 1);
+
+        let narrow = write(FuseList {
+            width: 8,
+            ..FuseList::default()
+        });
+        assert!(fields(&narrow, "*L")
+            .iter()
+            .all(|line| line.len() == 8 + 8 || line.starts_with("*L02192")));
+
+        let ones = write(FuseList {
+            default_fuse: DefaultFuse::One,
+            ..FuseList::default()
+        });
+        assert_eq!(fields(&ones, "*F"), vec!["*F1"]);
+        assert!(fields(&ones, "*L")
+            .iter()
+            .all(|line| line[8..].contains('0')));
+
+        // the fuses of unused product terms are 0, so most of them are
+        let auto = write(FuseList {
+            default_fuse: DefaultFuse::Auto,
+            ..FuseList::default()
+        });
+        assert_eq!(auto, sparse);
+
+        // all of them describe the same fuses and have valid checksums
+        let fuses = jedec::parse(&sparse).unwrap().fuses;
+        for jedec in [&full, &narrow, &ones, &auto] {
+            assert_eq!(jedec::parse(jedec).unwrap().fuses, fuses);
+        }
+
+        let options = Options {
+            fuse_list: FuseList {
+                width: 0,
+                ..FuseList::default()
+            },
+            ..Options::default()
+        };
+        assert!(to_jedec(&table_data, &config, None, &options).is_err());
+    }
 }
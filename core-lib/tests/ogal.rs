@@ -28,17 +28,27 @@ fn pin() {
 #[test]
 fn dff() {
     let code = r"
+    a, i0, i1, i2, c, d = b;
     a.dff;
     i[0..2].dff;
     c,d.dff;";
 
     let o_gal = OGal::new(
         Vec::new(),
-        Vec::new(),
+        vec![LookupTable::new(
+            vec![vec![false, true]; 6],
+            vec!["b"],
+            vec!["a", "i0", "i1", "i2", "c", "d"],
+            "",
+        )
+        .unwrap()],
         vec!["a", "i0", "i1", "i2", "c", "d"],
     );
 
     assert_eq!(Ok(o_gal), OGal::parse(code));
+
+    // a flip flop needs a function
+    assert!(OGal::parse("a.dff;").is_err());
 }
 
 #[test]
@@ -164,3 +174,16 @@ fn table_fill() {
 
     assert_eq!(Ok(o_gal), OGal::parse(code));
 }
+
+#[test]
+fn table_bit_order() {
+    // the first input pin of a table is the most significant bit of its index, the WinCUPL code
+    // lists the pins in the same order
+    let table_data = open_gal::parse("pin 2 = a;\npin 3 = b;\npin 23 = y;\ny = a & !b;").unwrap();
+    assert_eq!(table_data[0].input_pins, vec![2, 3]);
+    assert_eq!(table_data[0].table, vec![false, false, true, false]);
+
+    let wincupl = open_gal::to_wincupl(&table_data, None);
+    assert!(wincupl.contains("= [in_2p, in_3p];"));
+    assert!(wincupl.contains("  'b'10 => 'b'1;"));
+}
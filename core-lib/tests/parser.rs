@@ -1,5 +1,8 @@
 mod tests {
-    use open_gal::{parse, TableData};
+    use open_gal::diagnostic;
+    use open_gal::{
+        lint, parse, parse_fitted, CircuitConfig, GlobalTerm, OutputEnable, Severity, TableData,
+    };
 
     #[test]
     fn easy_gal() {
@@ -45,24 +48,40 @@ mod tests {
                 output_pin: 17,
                 table: vec![false, false, false, true],
                 enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
             },
             TableData {
                 input_pins: vec![13, 11],
                 output_pin: 19,
                 table: vec![false, true, true, false],
                 enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
             },
             TableData {
                 input_pins: vec![13, 11],
                 output_pin: 18,
                 table: vec![false, true, true, true],
                 enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
             },
             TableData {
                 input_pins: vec![3, 2],
                 output_pin: 23,
                 table: vec![true, true, false, true],
                 enable_flip_flop: true,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
             },
         ];
 
@@ -104,24 +123,40 @@ mod tests {
                 output_pin: 13,
                 table: vec![false, false, false, true],
                 enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
             },
             TableData {
                 input_pins: vec![1, 2],
                 output_pin: 14,
                 table: vec![false, true, true, true],
                 enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
             },
             TableData {
                 input_pins: vec![1, 2],
                 output_pin: 15,
                 table: vec![false, true, true, false],
                 enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
             },
             TableData {
                 input_pins: vec![1],
                 output_pin: 16,
                 table: vec![true, false],
                 enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
             },
         ];
 
@@ -130,4 +165,397 @@ mod tests {
             assert_eq!(input[i], output[i], "at {}", i);
         }
     }
+
+    #[test]
+    fn dont_care() {
+        let code = r"
+        pin 1, 2, 3 = i[0..2];
+        pin 14, 15 = mux, dec;
+        table(i0, i1, i2 -> mux) {
+            0-0 0
+            0-1 1
+            10- 0
+            11- 1
+        }
+
+        table(i0, i1 -> dec).fill(-) {
+            00 1
+            x1 0
+        }";
+
+        let input = parse(code).unwrap();
+        let output = vec![
+            TableData {
+                input_pins: vec![1, 2, 3],
+                output_pin: 14,
+                table: vec![false, true, false, true, false, false, true, true],
+                enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: false,
+                oe: None,
+                global: None,
+            },
+            TableData {
+                input_pins: vec![1, 2],
+                output_pin: 15,
+                table: vec![true, false, false, false],
+                enable_flip_flop: false,
+                dont_care: vec![2],
+                active_low: false,
+                oe: None,
+                global: None,
+            },
+        ];
+
+        assert_eq!(input, output);
+    }
+
+    #[test]
+    fn active_low() {
+        let code = r"
+        pin 1, 2 = !reset, en;
+        pin 23, 22 = !cs, q;
+        table(reset, en -> cs).fill(-) {
+            00 0
+            01 1
+        }
+
+        table(en -> q) {
+            0 0
+            1 1
+        }
+        q.invert;";
+
+        // the tables hold the levels of the pins, pin 1 is low while reset is active
+        let input = parse(code).unwrap();
+        let output = vec![
+            TableData {
+                input_pins: vec![1, 2],
+                output_pin: 23,
+                table: vec![true, true, true, false],
+                enable_flip_flop: false,
+                dont_care: vec![0, 1],
+                active_low: true,
+                oe: None,
+                global: None,
+            },
+            TableData {
+                input_pins: vec![2],
+                output_pin: 22,
+                table: vec![true, false],
+                enable_flip_flop: false,
+                dont_care: vec![],
+                active_low: true,
+                oe: None,
+                global: None,
+            },
+        ];
+
+        assert_eq!(input, output);
+
+        // `invert` is only a keyword after a `.`
+        let input = parse("pin 1 = invert; pin 23 = y; y = invert; y.invert;").unwrap();
+        assert_eq!(input[0].input_pins, vec![1]);
+        assert_eq!(input[0].table, vec![true, false]);
+    }
+
+    #[test]
+    fn output_enable() {
+        let code = r"
+        pin 1, 2 = a, !en;
+        pin 14 = y;
+        y = a;
+        y.oe = en;";
+
+        let input = parse(code).unwrap();
+        assert_eq!(
+            input[0].oe,
+            Some(OutputEnable {
+                input_pins: vec![2],
+                table: vec![true, false],
+            })
+        );
+
+        // an output enable needs a function for the output
+        assert!(parse("pin 14 = y; y.oe = 1;").is_err());
+
+        // `oe` is only a keyword after a `.`
+        let input = parse("pin 13 = oe; pin 14 = y; y = oe; y.oe = oe;").unwrap();
+        assert_eq!(input[0].input_pins, vec![13]);
+        assert_eq!(
+            input[0].oe,
+            Some(OutputEnable {
+                input_pins: vec![13],
+                table: vec![false, true],
+            })
+        );
+    }
+
+    #[test]
+    fn global_terms() {
+        let code = r"
+        pin 1, 2, 3 = clk, !reset, set;
+        pin 14 = q;
+        q = set;
+        q.dff;
+        ar = reset;
+        sp = set & reset;";
+
+        let input = parse(code).unwrap();
+        assert_eq!(input.len(), 3);
+        // a global term drives no pin, its output pin is ignored
+        assert_eq!(input[1].input_pins, vec![2]);
+        assert_eq!(input[1].table, vec![true, false]);
+        assert_eq!(input[1].global, Some(GlobalTerm::AsynchronousReset));
+        assert_eq!(input[2].global, Some(GlobalTerm::SynchronousPreset));
+
+        // every global term can only be defined once
+        assert!(parse("pin 2 = a; ar = a; ar = !a;").is_err());
+
+        // `ar` and `sp` are only keywords at the start of an equation
+        let input = parse("pin 2, 3 = ar, sp; pin 14 = q; q = ar & sp;").unwrap();
+        assert_eq!(input.len(), 1);
+        assert_eq!(input[0].input_pins, vec![2, 3]);
+    }
+
+    #[test]
+    fn fitted_pins() {
+        let code = r"
+        pin 23 = y;
+        y = a & b;
+        z = a | b | c;
+        z.dff;";
+
+        // without a device unassigned signals are an error
+        assert!(parse(code).is_err());
+
+        let (table_data, pinout) = parse_fitted(code, &CircuitConfig::gal22v10()).unwrap();
+        let pins: Vec<(u32, &str, bool)> = pinout
+            .iter()
+            .map(|assignment| (assignment.pin, assignment.name.as_str(), assignment.fixed))
+            .collect();
+
+        // z goes to a 16 term OLMC and pin 1 stays free for the clock
+        assert_eq!(
+            pins,
+            vec![
+                (2, "a", false),
+                (3, "b", false),
+                (4, "c", false),
+                (18, "z", false),
+                (23, "y", true),
+            ]
+        );
+        assert_eq!(table_data[0].input_pins, vec![2, 3]);
+        assert_eq!(table_data[1].output_pin, 18);
+    }
+
+    #[test]
+    fn nodes() {
+        let pins = r"
+        pin 1, 2, 3, 4 = a, b, c, d;
+        pin 14, 15 = y, z;";
+
+        let with_nodes = parse(&format!(
+            "{} node ab; ab = a & b; y = ab | c; z = ab ^ d;",
+            pins
+        ))
+        .unwrap();
+        let flat = parse(&format!("{} y = a & b | c; z = (a & b) ^ d;", pins)).unwrap();
+        assert_eq!(with_nodes, flat);
+
+        // nodes can read other nodes
+        let with_nodes = parse(&format!(
+            "{} node ab, abc; abc = ab | c; ab = a & b; y = abc & d;",
+            pins
+        ))
+        .unwrap();
+        let flat = parse(&format!("{} y = (a & b | c) & d;", pins)).unwrap();
+        assert_eq!(with_nodes, flat);
+
+        let cycle = parse(&format!("{} node p, q; p = q & a; q = p | b; y = p;", pins));
+        assert!(cycle.unwrap_err()[0].message.contains("p -> q -> p"));
+
+        // every node error is reported
+        let errors = parse(&format!("{} node m, y; y = m;", pins)).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|err| err.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "y is a node and can't be assigned to a pin",
+                "node m has no function"
+            ]
+        );
+
+        // a node has no pin
+        assert!(parse(&format!("{} node y; y = a;", pins)).is_err());
+        assert!(parse(&format!("{} node n; y = n;", pins)).is_err());
+
+        // `node` is only a keyword at the start of a statement which declares a name
+        let input = parse("pin 1 = node; pin 14 = y; y = node;").unwrap();
+        assert_eq!(input[0].input_pins, vec![1]);
+        let input = parse("pin 1 = a; pin 14 = node; node = a;").unwrap();
+        assert_eq!(input[0].output_pin, 14);
+    }
+
+    #[test]
+    fn multiple_drivers() {
+        let code = r"
+        pin 1, 2 = a, b;
+        pin 14 = y;
+        y = a;
+        table(a, b -> y) {
+            00 0
+            01 1
+            10 1
+            11 0
+        }";
+        let err = parse(code).unwrap_err().remove(0);
+        assert_eq!(err.code, diagnostic::MULTIPLE_DRIVERS);
+        assert_eq!(err.message, "y is driven more than once");
+        assert_eq!(err.span.map(|span| span.line), Some(5));
+        assert_eq!(err.labels[0].span.line, 4);
+
+        let err = parse("pin 1 = a; pin 14 = y;\ny.dff;")
+            .unwrap_err()
+            .remove(0);
+        assert_eq!(err.code, diagnostic::UNDRIVEN);
+        assert_eq!(err.span.map(|span| (span.line, span.column)), Some((2, 1)));
+
+        let err = parse("pin 1, 2 = a, b; pin 14 = y; y = a;\ny.oe = a;\ny.oe = b;")
+            .unwrap_err()
+            .remove(0);
+        assert_eq!(err.code, diagnostic::MULTIPLE_DRIVERS);
+        assert_eq!(err.message, "y has more than one output enable");
+        assert_eq!(err.span.map(|span| span.line), Some(3));
+        assert_eq!(err.labels[0].span.line, 2);
+
+        let err = parse("pin 1 = c; pin 14 = a;\npin 14 = b;\na = c; b = c;")
+            .unwrap_err()
+            .remove(0);
+        assert_eq!(err.code, diagnostic::PIN);
+        assert_eq!(err.message, "pin 14 is assigned to a and b");
+        assert_eq!(err.span.map(|span| span.line), Some(2));
+        assert_eq!(err.labels[0].span.line, 1);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let err = parse("pin 1 = a;\npin 14 = y;\ny = a , b;")
+            .unwrap_err()
+            .remove(0);
+        assert_eq!(err.code, diagnostic::SYNTAX);
+        assert_eq!(err.message, "expected `;`, found `,`");
+        let span = err.span.unwrap();
+        assert_eq!(
+            (span.offset, span.len, span.line, span.column),
+            (29, 1, 3, 7)
+        );
+        assert_eq!(
+            err.to_string(),
+            "error[E0001]: line 3:7: expected `;`, found `,`"
+        );
+
+        let err = parse("pin 1 = a; pin 14 = y; y = a").unwrap_err().remove(0);
+        assert_eq!(err.message, "expected `;`, found the end of the code");
+
+        // undefined names point to their first use
+        let err = parse("pin 1 = a;\npin 14 = y;\ny = a & b;")
+            .unwrap_err()
+            .remove(0);
+        assert_eq!(err.code, diagnostic::PIN);
+        assert_eq!(err.span.map(|span| (span.line, span.column)), Some((3, 9)));
+    }
+
+    #[test]
+    fn recovery() {
+        // the parser goes on after the `;` or `}` of a broken statement
+        let code = r"
+        pin 1, 2 = a, b;
+        pin 14, 15 = y, z;
+        y = a , b;
+        table(a, b -> z) {
+            00 0
+            11 1
+        }
+        q = a & b;
+        z = 2;";
+        let errors = parse(code).unwrap_err();
+        let lines: Vec<u32> = errors.iter().map(|err| err.span.unwrap().line).collect();
+        // the semantic checks run on the statements which could be read, q has no pin
+        assert_eq!(lines, vec![4, 7, 10, 9]);
+        assert_eq!(errors[1].code, diagnostic::TABLE);
+        assert_eq!(errors[3].code, diagnostic::PIN);
+
+        // every undefined name is reported
+        let errors = parse("pin 14 = y; y = a & b;").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|err| err.code == diagnostic::PIN));
+    }
+
+    #[test]
+    fn lints() {
+        let code = r"
+        pin 1, 2, 3 = a, b, c;
+        pin 14, 15, 16, 17 = y, z, w, v;
+        y = a & b | a & !b;
+        z = a & !a;
+        w = a & w;
+        table(a, b -> v).fill(0) {
+            00 0
+            01 1
+            10 1
+            11 1
+        }";
+        let warnings = lint(code, None);
+        let found: Vec<(&str, u32)> = warnings
+            .iter()
+            .map(|warning| (warning.code.as_str(), warning.span.unwrap().line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (diagnostic::UNUSED_PIN, 2),
+                (diagnostic::UNUSED_INPUT, 4),
+                (diagnostic::CONSTANT_OUTPUT, 5),
+                (diagnostic::FEEDBACK_LOOP, 6),
+                (diagnostic::FULL_FILL, 7),
+            ]
+        );
+        assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+        assert_eq!(warnings[1].message, "the function of y doesn't depend on b");
+        assert_eq!(warnings[2].message, "z is always 0");
+
+        // pin 2 of the GAL16V8 is an input
+        let code = "pin 3, 2 = a, q;\nq = a;\nq.dff;";
+        assert!(lint(code, None).is_empty());
+        let warnings = lint(code, Some(&CircuitConfig::gal16v8()));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, diagnostic::UNREGISTERED_DFF);
+        assert_eq!(warnings[0].span.unwrap().line, 3);
+
+        // a registered output may read itself
+        assert!(lint("pin 2, 14 = a, q;\nq = a & !q;\nq.dff;", None).is_empty());
+
+        let code = r"
+        pin 1, 2, 3 = a, b, c; // allow(unused_pin)
+        pin 14 = y;
+        // allow(constant_output, unused_input)
+        y = a & !a | b & !b;";
+        assert!(lint(code, None).is_empty());
+    }
+
+    #[test]
+    fn contradicting_rows() {
+        let code = r"
+        pin 1, 2 = i[0..1];
+        pin 14 = o;
+        table(i0, i1 -> o) {
+            0- 0
+            -0 1
+            11 1
+        }";
+
+        assert!(parse(code).is_err());
+    }
 }
@@ -1,5 +1,5 @@
 mod tests {
-    use open_gal::{to_wincupl, TableData};
+    use open_gal::{to_wincupl, GlobalTerm, OutputEnable, TableData};
     #[test]
     fn wincupl() {
         let head = vec![
@@ -18,6 +18,10 @@ mod tests {
             output_pin: 23,
             table: vec![false, false, false, true],
             enable_flip_flop: false,
+            dont_care: vec![],
+            active_low: false,
+            oe: None,
+            global: None,
         }];
 
         let mut out_vec = Vec::new();
@@ -59,6 +63,45 @@ mod tests {
             println!("{}", out_vec[line]);
         }
     }
+
+    #[test]
+    fn output_enable() {
+        let mut table_data = vec![TableData::new(vec![1], 23, vec![true, false], false)];
+        table_data[0].oe = Some(OutputEnable {
+            input_pins: vec![2, 3],
+            table: vec![false, false, true, false],
+        });
+
+        let code = to_wincupl(&table_data, None);
+        assert!(code.contains("Pin 2 = in_2p;\nPin 3 = in_3p;\n"));
+        assert!(code.ends_with("}\n\nout_23p.oe = in_2p & !in_3p;\n"));
+
+        table_data[0].oe = Some(OutputEnable {
+            input_pins: vec![],
+            table: vec![false],
+        });
+        assert!(to_wincupl(&table_data, None).ends_with("out_23p.oe = 'b'0;\n"));
+    }
+
+    #[test]
+    fn global_terms() {
+        let mut reset = TableData::new(vec![2], 0, vec![false, true], false);
+        reset.global = Some(GlobalTerm::AsynchronousReset);
+        let table_data = vec![
+            TableData::new(vec![1], 23, vec![true, false], true),
+            TableData::new(vec![1], 22, vec![false, true], true),
+            reset.clone(),
+        ];
+
+        let code = to_wincupl(&table_data, None);
+        assert!(code.contains("Pin 2 = in_2p;\n"));
+        assert!(code.ends_with("APPEND out_23p.ar = in_2p;\nAPPEND out_22p.ar = in_2p;\n"));
+
+        // without a register the global term has nothing to act on
+        let table_data = vec![TableData::new(vec![1], 23, vec![true, false], false), reset];
+        assert!(to_wincupl(&table_data, None)
+            .ends_with("/* ar has no registered output to act on */\n"));
+    }
 }
 
 /*
@@ -1,54 +1,53 @@
-use open_gal::CircuitConfig;
+use open_gal::{Diagnostic, Options};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 pub fn rs_compile(code: String, config: &str) -> JsValue {
-    let config = match config {
-        "g22v10" => CircuitConfig::new(
-            5892,
-            24,
-            vec![
-                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-            ],
-            vec![
-                (14, 8),
-                (15, 10),
-                (16, 12),
-                (17, 14),
-                (18, 16),
-                (19, 16),
-                (20, 14),
-                (21, 12),
-                (22, 10),
-                (23, 8),
-            ],
-            vec![(13, 42)],
-        ),
-        _ => {
-            let err: Result<String, String> =
-                Err(format!("CircuitConfig {} is not define", config));
+    let config = match open_gal::devices::get(config) {
+        Ok(config) => config,
+        Err(err) => {
+            let err: Result<String, Vec<String>> = Err(vec![err]);
             return serde_wasm_bindgen::to_value(&err).unwrap();
         }
     };
 
-    if let Ok(truth_tables) = open_gal::parse(&code) {
-        serde_wasm_bindgen::to_value(&open_gal::to_jedec(&truth_tables, &config, None)).unwrap()
-    } else {
-        return serde_wasm_bindgen::to_value(&open_gal::parse(&code)).unwrap();
+    match open_gal::parse_fitted(&code, &config) {
+        Ok((truth_tables, pinout)) => {
+            let mut options = Options::default();
+            if pinout.iter().any(|assignment| !assignment.fixed) {
+                options.notes = pinout.iter().map(|assignment| assignment.to_string()).collect();
+            }
+            let jedec = open_gal::to_jedec(&truth_tables, &config, None, &options)
+                .map_err(|err| vec![err.to_string()]);
+            serde_wasm_bindgen::to_value(&jedec).unwrap()
+        }
+        Err(err) => {
+            let err: Result<String, Vec<String>> = Err(messages(err));
+            serde_wasm_bindgen::to_value(&err).unwrap()
+        }
     }
 }
 
 #[wasm_bindgen]
 pub fn rs_transpile(code: String) -> JsValue {
-    if let Ok(table_data) = open_gal::parse(&code) {
-        let result: Result<String, String> = Ok(open_gal::to_wincupl(&table_data, None));
-        serde_wasm_bindgen::to_value(&result).unwrap()
-    } else {
-        serde_wasm_bindgen::to_value(&open_gal::parse(&code)).unwrap()
-    }
+    let result = open_gal::parse(&code)
+        .map(|table_data| open_gal::to_wincupl(&table_data, None))
+        .map_err(messages);
+    serde_wasm_bindgen::to_value(&result).unwrap()
 }
 
 #[wasm_bindgen]
 pub fn rs_tabledata(code: String) -> JsValue {
-    serde_wasm_bindgen::to_value(&open_gal::parse(&code)).unwrap()
+    let result = open_gal::parse(&code).map_err(messages);
+    serde_wasm_bindgen::to_value(&result).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn rs_devices() -> JsValue {
+    serde_wasm_bindgen::to_value(&open_gal::devices::list()).unwrap()
+}
+
+/// Every error of the code as one line, the editor prints them below each other.
+fn messages(diagnostics: Vec<Diagnostic>) -> Vec<String> {
+    diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect()
 }
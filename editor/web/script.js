@@ -1,4 +1,4 @@
-let galType = "g22v10";
+let galType = "GAL22V10";
 
 
 
@@ -13,7 +13,7 @@ function setup_editor(monaco, editor_el) {
     });
 
     monaco.languages.setMonarchTokensProvider("open-gal", {
-        keywords: ["fill", "count", "dff"],
+        keywords: ["fill", "count", "dff", "invert", "oe", "ar", "sp", "node"],
         typeKeywords: ["pin", "table"],
         operators: ["=", "!", "&", "|", "^", "->"],
         symbols: /[=><!~?:&|+\-*\/\^%]+/,
@@ -70,7 +70,7 @@ function run_code(editor, output_el) {
     console.log(result);
 
     if (result.Err != null) {
-        output_el.innerText += result.Err;
+        output_el.innerText += result.Err.join("\n");
     } else {
         download(result.Ok, "open_gal.jed");
         output_el.innerText += "Compiled successfully";
@@ -82,21 +82,21 @@ function run_code(editor, output_el) {
 function setup_cmd(editor, output_el) {
     const commands = {
         // add commands here
-        help: () => "Available commands: help, clear ...",
+        help: () => "Available commands: help, clear, compile, transpile, tabledata, devices, device ...",
         compile: (args) => {
             const code = editor.getValue();
             const result = wasm_bindgen.rs_compile(code, galType);
 
             if (args.length === 0) {
                 if (result.Err != null) {
-                    output_el.innerText += result.Err;
+                    output_el.innerText += result.Err.join("\n");
                 } else {
                     output_el.innerText += "Compiled successfully\n";
                     output_el.innerText += result.Ok;
                 }
             } else if (args.length === 2 && args[0] == ">") {
                 if (result.Err != null) {
-                    output_el.innerText += result.Err;
+                    output_el.innerText += result.Err.join("\n");
                 } else {
                     download(result.Ok, args[1]);
                     output_el.innerText += "Compiled successfully";
@@ -112,14 +112,14 @@ function setup_cmd(editor, output_el) {
 
             if (args.length === 0) {
                 if (result.Err != null) {
-                    output_el.innerText += result.Err;
+                    output_el.innerText += result.Err.join("\n");
                 } else {
                     output_el.innerText += "Transpiled successfully\nWin Cuple code:\n";
                     output_el.innerText += result.Ok;
                 }
             } else if (args.length === 2 && args[0] == ">") {
                 if (result.Err != null) {
-                    output_el.innerText += result.Err;
+                    output_el.innerText += result.Err.join("\n");
                 } else {
                     download(result.Ok, args[1]);
                     output_el.innerText += "Transpiled successfully";
@@ -134,13 +134,13 @@ function setup_cmd(editor, output_el) {
 
             if (args.length === 0) {
                 if (result.Err != null) {
-                    output_el.innerText += result.Err;
+                    output_el.innerText += result.Err.join("\n");
                 } else {
                     output_el.innerText += JSON.stringify(result.Ok, null, 2);
                 }
             } else if (args.length === 2 && args[0] == ">") {
                 if (result.Err != null) {
-                    output_el.innerText += result.Err;
+                    output_el.innerText += result.Err.join("\n");
                 } else {
                     download(JSON.stringify(result.Ok, null, 2), args[1]);
                 }
@@ -148,6 +148,29 @@ function setup_cmd(editor, output_el) {
                 output_el.innerText += "ERROR: unrecognized arguments for `tabledata`"
             }
         },
+        devices: () => {
+            for (const device of wasm_bindgen.rs_devices()) {
+                const marker = device.name === galType ? "* " : "  ";
+                output_el.innerText += `${marker}${device.name}: ${device.num_pins} pins, ${device.num_fuses} fuses, ${device.macrocells}\n`;
+            }
+        },
+        device: (args) => {
+            if (args.length === 0) {
+                output_el.innerText += galType;
+            } else if (args.length === 1) {
+                const device = wasm_bindgen.rs_devices().find((device) =>
+                    [device.name, ...device.aliases].some((name) => name.toLowerCase() === args[0].toLowerCase()));
+
+                if (device == null) {
+                    output_el.innerText += `ERROR: unknown device ${args[0]}, see \`devices\``;
+                } else {
+                    galType = device.name;
+                    output_el.innerText += `Device set to ${galType}`;
+                }
+            } else {
+                output_el.innerText += "ERROR: unrecognized arguments for `device`"
+            }
+        },
         clear: () => {
             output_el.innerText = "";
             return "";